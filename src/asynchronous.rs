@@ -0,0 +1,724 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Asynchronous state resolution
+//!
+//! This module provides the `AsyncCondition` trait for conditions which can
+//! only be evaluated asynchronously, e.g. because they require querying a
+//! remote service, as well as the means for resolving an issue's state using
+//! such conditions without blocking.
+//!
+//! The resolution performed by `IssueStateSet::issue_state_async()` yields the
+//! same result as `Resolvable::issue_state()`: states are visited in
+//! dependency order and the last enabled state is selected. Conditions are
+//! awaited one at a time, in the same order as in the synchronous resolution.
+//!
+//! If the evaluation of a condition fails, the resolution yields an
+//! `ErrorKind::ConditionFailed` error for the same state and condition as
+//! `Resolvable::issue_state()`.
+//!
+
+use std::future::Future;
+use std::pin::Pin;
+use std::result::Result as RResult;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+
+use context::Context;
+use error::*;
+use expression::Expression;
+use resolution::{deps_enabled, EnabledMap, IssueStateSet};
+use state::IssueState;
+
+
+
+
+/// Convenience type for a boxed, sendable future
+///
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+
+/// Trait for asynchronously evaluated issue state conditions
+///
+/// This is the asynchronous counterpart of `Condition`. Instead of a `bool`,
+/// `satisfied_by()` yields a future resolving to whether the condition is
+/// satisfied by the given issue.
+///
+pub trait AsyncCondition {
+    type Issue;
+
+    /// Check whether the condition is satisfied by an issue
+    ///
+    fn satisfied_by<'a>(&'a self, issue: &'a Self::Issue) -> BoxFuture<'a, bool>;
+
+    /// Check whether the condition is satisfied, reporting failures
+    ///
+    /// Like `Condition::try_satisfied_by()`, this function allows reporting
+    /// failures, e.g. an unreachable remote service, as errors rather than
+    /// considering the condition unsatisfied. The resolution of issue states is
+    /// performed using this function.
+    ///
+    /// The default implementation defers to `satisfied_by()`.
+    ///
+    fn try_satisfied_by<'a>(
+        &'a self,
+        issue: &'a Self::Issue
+    ) -> BoxFuture<'a, RResult<bool, BoxError>> {
+        Box::pin(OkFuture {inner: self.satisfied_by(issue)})
+    }
+
+    /// Check whether the condition is satisfied in a given context
+    ///
+    /// Like `Condition::try_satisfied_in()`, this function allows conditions
+    /// depending on the environment, e.g. the current time, to retrieve the
+    /// information from the context. The resolution of issue states via
+    /// `IssueStateSet::issue_state_async_in()` is performed using this
+    /// function.
+    ///
+    /// The default implementation defers to `try_satisfied_by()`.
+    ///
+    fn try_satisfied_in<'a>(
+        &'a self,
+        issue: &'a Self::Issue,
+        context: &'a Context
+    ) -> BoxFuture<'a, RResult<bool, BoxError>> {
+        let _ = context;
+        self.try_satisfied_by(issue)
+    }
+}
+
+
+/// Asynchronous evaluation of expressions
+///
+/// Sub-expressions are evaluated in order. Like their synchronous counterpart,
+/// conjunctions and disjunctions short-circuit: the remaining sub-expressions
+/// are not evaluated once the result is known. A failing sub-expression
+/// decides the result of `try_satisfied_by()`.
+///
+impl<C> AsyncCondition for Expression<C>
+    where C: AsyncCondition + Sync,
+          C::Issue: Sync
+{
+    type Issue = C::Issue;
+
+    fn satisfied_by<'a>(&'a self, issue: &'a Self::Issue) -> BoxFuture<'a, bool> {
+        let evaluate = Self::satisfied_by;
+        match *self {
+            Expression::Atom(ref c) => c.satisfied_by(issue),
+            Expression::And(ref e) => Box::pin(ExpressionFuture::new(e, issue, true, evaluate)),
+            Expression::Or(ref e) => Box::pin(ExpressionFuture::new(e, issue, false, evaluate)),
+            Expression::Not(ref e) => {
+                let inner = e.satisfied_by(issue);
+                Box::pin(NotFuture {inner})
+            },
+        }
+    }
+
+    fn try_satisfied_by<'a>(
+        &'a self,
+        issue: &'a Self::Issue
+    ) -> BoxFuture<'a, RResult<bool, BoxError>> {
+        let evaluate = Self::try_satisfied_by;
+        match *self {
+            Expression::Atom(ref c) => c.try_satisfied_by(issue),
+            Expression::And(ref e) => Box::pin(ExpressionFuture::new(e, issue, true, evaluate)),
+            Expression::Or(ref e) => Box::pin(ExpressionFuture::new(e, issue, false, evaluate)),
+            Expression::Not(ref e) => {
+                let inner = e.try_satisfied_by(issue);
+                Box::pin(NotFuture {inner})
+            },
+        }
+    }
+
+    fn try_satisfied_in<'a>(
+        &'a self,
+        issue: &'a Self::Issue,
+        context: &'a Context
+    ) -> BoxFuture<'a, RResult<bool, BoxError>> {
+        let evaluate = move |e: &'a Self, issue| e.try_satisfied_in(issue, context);
+        match *self {
+            Expression::Atom(ref c) => c.try_satisfied_in(issue, context),
+            Expression::And(ref e) => Box::pin(ExpressionFuture::new(e, issue, true, evaluate)),
+            Expression::Or(ref e) => Box::pin(ExpressionFuture::new(e, issue, false, evaluate)),
+            Expression::Not(ref e) => {
+                let inner = e.try_satisfied_in(issue, context);
+                Box::pin(NotFuture {inner})
+            },
+        }
+    }
+}
+
+
+/// Outcome of the evaluation of a condition
+///
+/// This trait abstracts over plain results and results of fallible
+/// evaluations, allowing the same futures to be used for both.
+///
+trait Outcome: Sized {
+    /// Create an outcome from a plain result
+    ///
+    fn from_bool(value: bool) -> Self;
+
+    /// Check whether the outcome differs from the neutral value
+    ///
+    /// If this function returns `true`, the outcome decides the result of a
+    /// conjunction or disjunction with the given neutral value.
+    ///
+    fn decides(&self, neutral: bool) -> bool;
+
+    /// Negate the outcome
+    ///
+    fn negate(self) -> Self;
+}
+
+
+impl Outcome for bool {
+    fn from_bool(value: bool) -> Self {
+        value
+    }
+
+    fn decides(&self, neutral: bool) -> bool {
+        *self != neutral
+    }
+
+    fn negate(self) -> Self {
+        !self
+    }
+}
+
+
+impl Outcome for RResult<bool, BoxError> {
+    fn from_bool(value: bool) -> Self {
+        Ok(value)
+    }
+
+    fn decides(&self, neutral: bool) -> bool {
+        self.as_ref().map(|v| *v != neutral).unwrap_or(true)
+    }
+
+    fn negate(self) -> Self {
+        self.map(|v| !v)
+    }
+}
+
+
+/// Future evaluating a conjunction or disjunction of expressions
+///
+struct ExpressionFuture<'a, C, T, F>
+    where C: AsyncCondition + 'a,
+          C::Issue: 'a
+{
+    expressions: &'a [Expression<C>],
+    issue: &'a C::Issue,
+    /// Value of the composition if no sub-expression decides otherwise
+    ///
+    /// This is `true` for conjunctions and `false` for disjunctions.
+    neutral: bool,
+    /// Function for evaluating a sub-expression
+    evaluate: F,
+    current: Option<BoxFuture<'a, T>>,
+}
+
+
+impl<'a, C, T, F> ExpressionFuture<'a, C, T, F>
+    where C: AsyncCondition + 'a,
+          C::Issue: 'a,
+          F: Fn(&'a Expression<C>, &'a C::Issue) -> BoxFuture<'a, T>
+{
+    fn new(
+        expressions: &'a [Expression<C>],
+        issue: &'a C::Issue,
+        neutral: bool,
+        evaluate: F
+    ) -> Self {
+        Self {expressions, issue, neutral, evaluate, current: None}
+    }
+}
+
+
+impl<'a, C, T, F> Future for ExpressionFuture<'a, C, T, F>
+    where C: AsyncCondition + Sync + 'a,
+          C::Issue: Sync + 'a,
+          T: Outcome,
+          F: Fn(&'a Expression<C>, &'a C::Issue) -> BoxFuture<'a, T> + Unpin
+{
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<T> {
+        let this = self.get_mut();
+        loop {
+            if let Some(ref mut current) = this.current {
+                match current.as_mut().poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(v) if v.decides(this.neutral) => return Poll::Ready(v),
+                    Poll::Ready(_) => (),
+                }
+            }
+
+            match this.expressions.split_first() {
+                Some((first, rest)) => {
+                    this.current = Some((this.evaluate)(first, this.issue));
+                    this.expressions = rest;
+                },
+                None => return Poll::Ready(T::from_bool(this.neutral)),
+            }
+        }
+    }
+}
+
+
+/// Future negating the result of another future
+///
+struct NotFuture<'a, T> {
+    inner: BoxFuture<'a, T>,
+}
+
+
+impl<'a, T> Future for NotFuture<'a, T>
+    where T: Outcome
+{
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<T> {
+        self.inner.as_mut().poll(cx).map(Outcome::negate)
+    }
+}
+
+
+/// Future wrapping the result of another future in `Ok`
+///
+struct OkFuture<'a> {
+    inner: BoxFuture<'a, bool>,
+}
+
+
+impl<'a> Future for OkFuture<'a> {
+    type Output = RResult<bool, BoxError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<Self::Output> {
+        self.inner.as_mut().poll(cx).map(Ok)
+    }
+}
+
+
+
+
+impl<C> IssueStateSet<C>
+    where C: AsyncCondition
+{
+    /// Resolve the state for a given issue asynchronously
+    ///
+    /// This function is the asynchronous counterpart of
+    /// `Resolvable::issue_state()`. The returned future yields the state
+    /// selected for the given issue, or `None` if no state is enabled.
+    /// Conditions are evaluated using `AsyncCondition::try_satisfied_by()`.
+    ///
+    pub fn issue_state_async<'a>(&'a self, issue: &'a C::Issue) -> IssueStateFuture<'a, C> {
+        IssueStateFuture {
+            states: self.iter().as_slice(),
+            issue,
+            context: None,
+            enabled_map: EnabledMap::default(),
+            conditions: 0,
+            current: None,
+            selected: None,
+        }
+    }
+
+    /// Resolve the state for a given issue asynchronously in a given context
+    ///
+    /// This function is the asynchronous counterpart of
+    /// `Resolvable::issue_state_in()`. Conditions are evaluated in the context
+    /// provided, using `AsyncCondition::try_satisfied_in()`.
+    ///
+    pub fn issue_state_async_in<'a>(
+        &'a self,
+        issue: &'a C::Issue,
+        context: &'a Context
+    ) -> IssueStateFuture<'a, C> {
+        IssueStateFuture {context: Some(context), ..self.issue_state_async(issue)}
+    }
+}
+
+
+/// Future resolving an issue's state
+///
+/// This future is created by `IssueStateSet::issue_state_async()`.
+///
+pub struct IssueStateFuture<'a, C>
+    where C: AsyncCondition + 'a,
+          C::Issue: 'a
+{
+    /// States not yet fully evaluated, ordered by dependency
+    states: &'a [Arc<IssueState<C>>],
+    issue: &'a C::Issue,
+    /// Context in which conditions are evaluated, if any
+    context: Option<&'a Context>,
+    enabled_map: EnabledMap<C>,
+    /// Number of conditions of the current state already known to be satisfied
+    conditions: usize,
+    /// Evaluation of the current condition, if in progress
+    current: Option<BoxFuture<'a, RResult<bool, BoxError>>>,
+    /// The last enabled state encountered so far
+    selected: Option<&'a Arc<IssueState<C>>>,
+}
+
+
+impl<'a, C> IssueStateFuture<'a, C>
+    where C: AsyncCondition + 'a,
+          C::Issue: 'a
+{
+    /// Record whether the current state is enabled and advance to the next one
+    ///
+    fn finish_state(&mut self, enabled: bool) {
+        if let Some((state, rest)) = self.states.split_first() {
+            if enabled {
+                self.selected = Some(state);
+            }
+            self.enabled_map.insert(state.clone(), enabled);
+            self.states = rest;
+        }
+        self.conditions = 0;
+        self.current = None;
+    }
+}
+
+
+impl<'a, C> Future for IssueStateFuture<'a, C>
+    where C: AsyncCondition + 'a,
+          C::Issue: 'a
+{
+    type Output = Result<Option<Arc<IssueState<C>>>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            if let Some(ref mut current) = this.current {
+                match current.as_mut().poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(true)) => this.conditions += 1,
+                    Poll::Ready(Ok(false)) => {
+                        this.finish_state(false);
+                        continue;
+                    },
+                    Poll::Ready(Err(e)) => {
+                        let state = this.states[0].name().clone();
+                        let kind = ErrorKind::ConditionFailed {state, condition: this.conditions};
+                        return Poll::Ready(Err(Error::with_source(kind, e)));
+                    },
+                }
+                this.current = None;
+            }
+
+            let state = match this.states.first() {
+                Some(state) => state,
+                None => return Poll::Ready(Ok(this.selected.cloned())),
+            };
+
+            // Like in the synchronous resolution, the extended states are only
+            // checked after all of the state's own conditions are satisfied.
+            match state.conditions.get(this.conditions) {
+                Some(condition) => this.current = Some(match this.context {
+                    Some(context) => condition.try_satisfied_in(this.issue, context),
+                    None => condition.try_satisfied_by(this.issue),
+                }),
+                None => match deps_enabled(state, &this.enabled_map) {
+                    Ok(enabled) => this.finish_state(enabled),
+                    Err(e) => return Poll::Ready(Err(e)),
+                },
+            }
+        }
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resolution::Resolvable;
+    use std::collections;
+    use std::task::Waker;
+    use test::{example_states, TestCond};
+
+    /// Future yielding a value only after being polled once
+    ///
+    struct YieldOnce {
+        value: bool,
+        yielded: bool,
+    }
+
+    impl Future for YieldOnce {
+        type Output = bool;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<bool> {
+            if self.yielded {
+                Poll::Ready(self.value)
+            } else {
+                self.yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    impl AsyncCondition for TestCond {
+        type Issue = <TestCond as ::condition::Condition>::Issue;
+
+        fn satisfied_by<'a>(&'a self, issue: &'a Self::Issue) -> BoxFuture<'a, bool> {
+            let value = ::condition::Condition::satisfied_by(self, issue);
+            Box::pin(YieldOnce {value, yielded: false})
+        }
+    }
+
+    /// Condition failing if the issue lacks the flag
+    ///
+    struct StrictCond(TestCond);
+
+    impl StrictCond {
+        fn evaluate(&self, issue: &<Self as AsyncCondition>::Issue) -> RResult<bool, BoxError> {
+            let name = self.0.to_string();
+            issue
+                .get(name.as_str())
+                .cloned()
+                .ok_or_else(|| format!("no value for {}", name).into())
+        }
+    }
+
+    impl ::condition::Condition for StrictCond {
+        type Issue = <TestCond as ::condition::Condition>::Issue;
+
+        fn satisfied_by(&self, issue: &Self::Issue) -> bool {
+            ::condition::Condition::satisfied_by(&self.0, issue)
+        }
+
+        fn try_satisfied_by(&self, issue: &Self::Issue) -> RResult<bool, BoxError> {
+            self.evaluate(issue)
+        }
+    }
+
+    impl AsyncCondition for StrictCond {
+        type Issue = <TestCond as AsyncCondition>::Issue;
+
+        fn satisfied_by<'a>(&'a self, issue: &'a Self::Issue) -> BoxFuture<'a, bool> {
+            self.0.satisfied_by(issue)
+        }
+
+        fn try_satisfied_by<'a>(
+            &'a self,
+            issue: &'a Self::Issue
+        ) -> BoxFuture<'a, RResult<bool, BoxError>> {
+            Box::pin(::std::future::ready(self.evaluate(issue)))
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let mut cx = TaskContext::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(v) = future.as_mut().poll(&mut cx) {
+                return v;
+            }
+        }
+    }
+
+    #[test]
+    fn async_resolution() {
+        let states = example_states();
+
+        let issues = vec![
+            (vec![], "new"),
+            (vec!["acked"], "acknowledged"),
+            (vec!["acked", "assigned"], "assigned"),
+            (vec!["assigned"], "new"),
+            (vec!["closed"], "closed"),
+            (vec!["acked", "assigned", "closed"], "closed"),
+        ];
+
+        for (flags, name) in issues {
+            let issue: collections::BTreeMap<_, _> = flags
+                .into_iter()
+                .map(|f| (f, true))
+                .collect();
+            let expected = states
+                .issue_state(&issue)
+                .expect("Failed to determine state")
+                .map(|s| s.name().clone());
+            let actual = block_on(states.issue_state_async(&issue))
+                .expect("Failed to determine state")
+                .map(|s| s.name().clone());
+            assert_eq!(actual, expected);
+            assert_eq!(actual.as_deref(), Some(name));
+        }
+    }
+
+    #[test]
+    fn async_failing_conditions() {
+        use std::error::Error as StdError;
+
+        let new : Arc<IssueState<StrictCond>> = IssueState::new("new".to_string()).into();
+        let acked : Arc<IssueState<StrictCond>> = {
+            let mut tmp = IssueState::new("acknowledged".to_string());
+            tmp.conditions = vec![StrictCond("acked".into()), StrictCond("valid".into())];
+            tmp.add_overridden(vec![new.clone()]);
+            tmp
+        }.into();
+        let states: IssueStateSet<_> = vec![new, acked].into();
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("acked", true);
+
+        let err = match block_on(states.issue_state_async(&issue)) {
+            Ok(_) => panic!("Failed to report failure."),
+            Err(err) => err,
+        };
+        match *err.kind() {
+            ErrorKind::ConditionFailed {ref state, condition} => {
+                assert_eq!(state, "acknowledged");
+                assert_eq!(condition, 1);
+            },
+            _ => panic!("Unexpected error: {}", err),
+        }
+        assert_eq!(err.source().expect("Missing source.").to_string(), "no value for valid");
+
+        // Conditions following an unsatisfied one are not evaluated
+        issue.insert("acked", false);
+        let state = block_on(states.issue_state_async(&issue))
+            .expect("Failed to determine state.")
+            .expect("Wrongly determined no state.");
+        assert_eq!(state.name(), "new");
+
+        // Failures are propagated through expressions
+        let atom = |name: &'static str| Expression::Atom(StrictCond(name.into()));
+        let expression = Expression::Or(vec![atom("acked"), atom("valid")]);
+        assert!(block_on(expression.try_satisfied_by(&issue)).is_err());
+        let expression = Expression::And(vec![atom("acked"), atom("valid")]);
+        assert!(!block_on(expression.try_satisfied_by(&issue)).expect("Failed to evaluate."));
+    }
+
+    #[test]
+    fn async_expressions() {
+        let issue: collections::BTreeMap<_, _> = vec![("foo", true), ("bar", false)]
+            .into_iter()
+            .collect();
+        let atom = |name: &'static str| Expression::Atom(TestCond::from(name));
+
+        let check = |e: Expression<TestCond>| block_on(AsyncCondition::satisfied_by(&e, &issue));
+        assert!(check(atom("foo")));
+        assert!(!check(atom("bar")));
+        assert!(check(Expression::And(vec![])));
+        assert!(!check(Expression::Or(vec![])));
+        assert!(!check(Expression::And(vec![atom("foo"), atom("bar")])));
+        assert!(check(Expression::Or(vec![atom("bar"), atom("foo")])));
+        assert!(check(Expression::Not(Box::new(atom("bar")))));
+    }
+
+    #[test]
+    fn async_failing_blocked_conditions() {
+        let new : Arc<IssueState<StrictCond>> = IssueState::new("new".to_string()).into();
+        let acked : Arc<IssueState<StrictCond>> = {
+            let mut tmp = IssueState::new("acknowledged".to_string());
+            tmp.conditions = vec![StrictCond("acked".into())];
+            tmp.add_overridden(vec![new.clone()]);
+            tmp
+        }.into();
+        let assigned : Arc<IssueState<StrictCond>> = {
+            let mut tmp = IssueState::new("assigned".to_string());
+            tmp.conditions = vec![StrictCond("assigned".into())];
+            tmp.add_extended(vec![acked.clone()]);
+            tmp
+        }.into();
+        let states: IssueStateSet<_> = vec![new, acked, assigned].into();
+
+        // The extended state is disabled, but the condition is still evaluated
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("acked", false);
+
+        for err in [states.issue_state(&issue), block_on(states.issue_state_async(&issue))] {
+            let err = match err {
+                Ok(_) => panic!("Failed to report failure."),
+                Err(err) => err,
+            };
+            match *err.kind() {
+                ErrorKind::ConditionFailed {ref state, condition} => {
+                    assert_eq!(state, "assigned");
+                    assert_eq!(condition, 0);
+                },
+                _ => panic!("Unexpected error: {}", err),
+            }
+        }
+    }
+
+    #[test]
+    fn async_context() {
+        use std::future;
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        // Condition satisfied before a given point in time
+        struct Before(SystemTime);
+
+        impl AsyncCondition for Before {
+            type Issue = ();
+
+            fn satisfied_by<'a>(&'a self, _: &'a ()) -> BoxFuture<'a, bool> {
+                Box::pin(future::ready(SystemTime::now() < self.0))
+            }
+
+            fn try_satisfied_in<'a>(
+                &'a self,
+                _: &'a (),
+                context: &'a Context
+            ) -> BoxFuture<'a, RResult<bool, BoxError>> {
+                Box::pin(future::ready(Ok(context.now() < self.0)))
+            }
+        }
+
+        let day = Duration::from_secs(86400);
+        let before = |days| Expression::Atom(Before(UNIX_EPOCH + day * days));
+        let early : Arc<IssueState<_>> = IssueState::new("early".to_string()).into();
+        let late : Arc<IssueState<_>> = {
+            let mut tmp = IssueState::new("late".to_string());
+            let condition = Expression::And(vec![before(20), before(10)]);
+            tmp.conditions = vec![Expression::Not(Box::new(condition))];
+            tmp.add_overridden(vec![early.clone()]);
+            tmp
+        }.into();
+        let states: IssueStateSet<_> = vec![early, late].into();
+
+        let state_at = |days| {
+            let context = Context::new(UNIX_EPOCH + day * days);
+            block_on(states.issue_state_async_in(&(), &context))
+                .expect("Failed to determine state.")
+                .map(|s| s.name().clone())
+        };
+        assert_eq!(state_at(5).as_deref(), Some("early"));
+        assert_eq!(state_at(15).as_deref(), Some("late"));
+        let state = block_on(states.issue_state_async(&()))
+            .expect("Failed to determine state.")
+            .map(|s| s.name().clone());
+        assert_eq!(state.as_deref(), Some("late"));
+    }
+}
@@ -0,0 +1,331 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Command-line tool for validating issue states and resolving issues' states
+//!
+//! The tool loads issue states from YAML specifications. Conditions are
+//! interpreted as `MetadataCondition`s on the metadata of issues, which are
+//! loaded from JSON or YAML files containing a map each.
+//!
+
+extern crate issue_states;
+extern crate serde_json;
+extern crate yaml_rust;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use issue_states::expression::{Expression, ExpressionFactory};
+use issue_states::metadata::{MetadataCondition, MetadataConditionFactory, ToValue, Value};
+use issue_states::resolution::Resolvable;
+use issue_states::{yaml, IssueStateSet};
+
+
+
+
+const USAGE: &str = "Usage:
+    issue-states check <spec>...
+    issue-states reachable <spec>...
+    issue-states resolve [--strict] <spec> <issue>...
+
+Commands:
+    check       Validate the issue state specifications given, reporting
+                inconsistencies such as states which are never selected and
+                warning about redundant relations
+    reachable   Report, for each state, example metadata for which the state
+                is selected, failing if any state can never be selected
+    resolve     Resolve the state of the issues given using the specification
+
+Issue states are specified in YAML. Issues are represented by JSON (if the file
+name ends with '.json') or YAML files containing the issue's metadata as a map.
+
+Options:
+    --strict    Report an error if the state of an issue is ambiguous";
+
+
+/// Owned value of a piece of metadata
+///
+enum Field {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    List(Vec<Field>),
+}
+
+
+impl ToValue for Field {
+    fn to_value(&self) -> Value<'_> {
+        match *self {
+            Field::String(ref s) => Value::String(s.as_str()),
+            Field::Integer(i) => Value::Integer(i),
+            Field::Float(f) => Value::Float(f),
+            Field::Bool(b) => Value::Bool(b),
+            Field::List(ref l) => l.to_value(),
+        }
+    }
+}
+
+
+type Issue = BTreeMap<String, Field>;
+type Condition = Expression<MetadataCondition<Issue>>;
+type States = IssueStateSet<Condition>;
+
+
+
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let code = match run(&args) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(msg) => {
+            eprintln!("{}\n\n{}", msg, USAGE);
+            2
+        },
+    };
+    process::exit(code)
+}
+
+
+/// Run the command specified by the arguments
+///
+/// Returns whether the command succeeded or an error message if the arguments
+/// are invalid.
+///
+fn run(args: &[String]) -> Result<bool, String> {
+    let (command, args) = args.split_first().ok_or_else(|| "No command given".to_owned())?;
+    match command.as_str() {
+        "check" if !args.is_empty() => {
+            let failed = args.iter().filter(|spec| !check(spec)).count();
+            Ok(failed == 0)
+        },
+        "reachable" if !args.is_empty() => {
+            let failed = args.iter().filter(|spec| !reachable(spec)).count();
+            Ok(failed == 0)
+        },
+        "resolve" => {
+            let strict = args.first().map(|a| a == "--strict").unwrap_or(false);
+            let args = if strict { &args[1..] } else { args };
+            let (spec, issues) = args.split_first().ok_or_else(|| "No specification given".to_owned())?;
+            Ok(resolve(spec, issues, strict))
+        },
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(true)
+        },
+        _ => Err(format!("Invalid command '{}'", command)),
+    }
+}
+
+
+/// Check a single specification, reporting any errors
+///
+/// Advisory diagnostics are reported as warnings, which do not cause the check
+/// to fail.
+///
+fn check(spec: &str) -> bool {
+    match load_spec(spec) {
+        Ok(states) => {
+            let mut ok = true;
+            for diagnostic in states.validate() {
+                if diagnostic.is_error() {
+                    eprintln!("{}: {}", spec, diagnostic);
+                    ok = false;
+                } else {
+                    eprintln!("{}: warning: {}", spec, diagnostic);
+                }
+            }
+            if ok {
+                println!("{}: ok", spec);
+            }
+            ok
+        },
+        Err(msg) => {
+            eprintln!("{}", msg);
+            false
+        },
+    }
+}
+
+
+/// Analyse which states of a specification can be selected, printing examples
+///
+fn reachable(spec: &str) -> bool {
+    let results = load_spec(spec)
+        .and_then(|states| states.reachability().map_err(|e| format!("{}: {}", spec, e)));
+    let results = match results {
+        Ok(results) => results,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            return false
+        },
+    };
+
+    let mut ok = true;
+    for result in results {
+        match result.example() {
+            Some(example) => {
+                let fields: Vec<_> = example.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+                println!("{}: {}: {{{}}}", spec, result.state().name(), fields.join(", "));
+            },
+            None => {
+                eprintln!("{}: {}: unreachable", spec, result.state().name());
+                ok = false;
+            },
+        }
+    }
+    ok
+}
+
+
+/// Resolve the states of the given issues, printing them
+///
+fn resolve(spec: &str, issues: &[String], strict: bool) -> bool {
+    let states = match load_spec(spec) {
+        Ok(states) => states,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            return false
+        },
+    };
+
+    let mut ok = true;
+    for path in issues {
+        let state = load_issue(path).and_then(|issue| if strict {
+            states.issue_state_strict(&issue)
+        } else {
+            states.issue_state(&issue)
+        }.map_err(|e| format!("{}: {}", path, e)));
+
+        match state {
+            Ok(Some(state)) => println!("{}: {}", path, state.name()),
+            Ok(None) => println!("{}: (none)", path),
+            Err(msg) => {
+                eprintln!("{}", msg);
+                ok = false;
+            },
+        }
+    }
+    ok
+}
+
+
+/// Load and validate a specification from a YAML file
+///
+fn load_spec(path: &str) -> Result<States, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+
+    let mut parser = yaml_rust::parser::Parser::new(text.chars());
+    let states = yaml::parse_issue_states(
+        &mut parser,
+        ExpressionFactory::<MetadataConditionFactory<Issue>>::default()
+    ).map_err(|e| format!("{}: {}", path, e))?;
+
+    // The parser only yields relations to states appearing earlier in the
+    // document. Still, we let the set check the dependencies on its own. States
+    // are ordered by name only, so precompiled patterns don't affect the set.
+    #[allow(clippy::mutable_key_type)]
+    let set: std::collections::BTreeSet<_> = states.iter().cloned().collect();
+    if set.len() != states.iter().count() {
+        return Err(format!("{}: duplicate state names", path));
+    }
+    IssueStateSet::from_set(set).map_err(|e| format!("{}: {}", path, e))?;
+
+    Ok(states)
+}
+
+
+/// Load an issue's metadata from a JSON or YAML file
+///
+fn load_issue(path: &str) -> Result<Issue, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+
+    let is_json = Path::new(path).extension().map(|ext| ext == "json").unwrap_or(false);
+    let issue = if is_json {
+        match serde_json::from_str(text.as_str()).map_err(|e| format!("{}: {}", path, e))? {
+            serde_json::Value::Object(map) => map
+                .into_iter()
+                .filter_map(|(k, v)| json_field(v).map(|v| (k, v)))
+                .collect(),
+            _ => return Err(format!("{}: expected object", path)),
+        }
+    } else {
+        let doc = yaml_rust::YamlLoader::load_from_str(text.as_str())
+            .map_err(|e| format!("{}: {}", path, e))?
+            .into_iter()
+            .next();
+        match doc {
+            Some(yaml_rust::Yaml::Hash(map)) => map
+                .into_iter()
+                .filter_map(|(k, v)| match (k, yaml_field(v)) {
+                    (yaml_rust::Yaml::String(k), Some(v)) => Some((k, v)),
+                    _ => None,
+                })
+                .collect(),
+            _ => return Err(format!("{}: expected map", path)),
+        }
+    };
+
+    Ok(issue)
+}
+
+
+/// Convert a JSON value to a `Field`
+///
+/// Null values and objects are not representable and yield `None`.
+///
+fn json_field(value: serde_json::Value) -> Option<Field> {
+    match value {
+        serde_json::Value::String(s) => Some(Field::String(s)),
+        serde_json::Value::Bool(b) => Some(Field::Bool(b)),
+        serde_json::Value::Number(n) => Some(n
+            .as_i64()
+            .map(Field::Integer)
+            .or_else(|| n.as_f64().map(Field::Float))
+            .unwrap_or_else(|| Field::String(n.to_string()))),
+        serde_json::Value::Array(a) => Some(Field::List(a.into_iter().filter_map(json_field).collect())),
+        _ => None,
+    }
+}
+
+
+/// Convert a YAML node to a `Field`
+///
+/// Null values and maps are not representable and yield `None`.
+///
+fn yaml_field(value: yaml_rust::Yaml) -> Option<Field> {
+    match value {
+        yaml_rust::Yaml::Real(s) => Some(s.parse().map(Field::Float).unwrap_or(Field::String(s))),
+        yaml_rust::Yaml::String(s) => Some(Field::String(s)),
+        yaml_rust::Yaml::Integer(i) => Some(Field::Integer(i)),
+        yaml_rust::Yaml::Boolean(b) => Some(Field::Bool(b)),
+        yaml_rust::Yaml::Array(a) => Some(Field::List(a.into_iter().filter_map(yaml_field).collect())),
+        _ => None,
+    }
+}
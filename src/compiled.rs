@@ -0,0 +1,615 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Compiled issue state sets
+//!
+//! This module provides the `CompiledStateSet`, a representation of an
+//! `IssueStateSet` optimized for resolving the states of many issues. Each
+//! state is assigned an index at construction and the states it extends are
+//! kept as lists of indices. Resolution only requires a buffer of flags, which
+//! may be reused across issues, so no allocations are performed per issue.
+//!
+//! For condition types implementing `Eq` and `Hash`, a set may also be compiled
+//! such that identical conditions appearing in multiple states are evaluated
+//! at most once per issue.
+//!
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::Arc;
+
+use condition::Condition;
+use context::Context;
+use error::*;
+use resolution::{IssueStateSet, Resolvable};
+use state::{IssueState, StateRelation};
+
+
+
+
+/// Scratch buffer for the resolution via a `CompiledStateSet`
+///
+/// The buffer holds one flag per state, indicating whether the state is
+/// enabled, and the results of conditions already evaluated. A buffer may be
+/// reused for any number of resolutions.
+///
+#[derive(Default, Clone)]
+pub struct Scratch {
+    enabled: Vec<bool>,
+    conditions: Vec<Option<bool>>,
+}
+
+
+
+
+/// Table of distinct conditions
+///
+struct ConditionTable {
+    /// Indices of distinct conditions, for all states in sequence
+    ids: Box<[usize]>,
+    /// Offsets into `ids` for each state, followed by the total length
+    offsets: Box<[usize]>,
+    /// Number of distinct conditions
+    count: usize,
+}
+
+
+
+
+/// Compiled set of issue states
+///
+/// A `CompiledStateSet` is constructed from an `IssueStateSet` and yields the
+/// same states for any given issue.
+///
+pub struct CompiledStateSet<C>
+    where C: Condition
+{
+    /// The states, in the order of the original set
+    states: Box<[Arc<IssueState<C>>]>,
+    /// Indices of extended states, for all states in sequence
+    extends: Box<[usize]>,
+    /// Offsets into `extends` for each state, followed by the total length
+    offsets: Box<[usize]>,
+    /// Distinct conditions, if conditions are to be memoized
+    conditions: Option<ConditionTable>,
+}
+
+
+impl<C> CompiledStateSet<C>
+    where C: Condition
+{
+    /// Compile a set of issue states
+    ///
+    /// If a state extends a state which does not precede it in the set, an
+    /// `ErrorKind::DependencyError` is returned.
+    ///
+    pub fn new(states: &IssueStateSet<C>) -> Result<Self> {
+        let mut indices = BTreeMap::new();
+        let mut extends = Vec::default();
+        let mut offsets = vec![0];
+
+        for (index, state) in states.iter().enumerate() {
+            for (dependency, relation) in state.relations.iter() {
+                if *relation == StateRelation::Extends {
+                    let dependency = indices
+                        .get(dependency)
+                        .ok_or_else(|| Error::from(ErrorKind::DependencyError))?;
+                    extends.push(*dependency);
+                }
+            }
+
+            offsets.push(extends.len());
+            indices.insert(state, index);
+        }
+
+        Ok(Self {
+            states: states.iter().cloned().collect::<Vec<_>>().into_boxed_slice(),
+            extends: extends.into_boxed_slice(),
+            offsets: offsets.into_boxed_slice(),
+            conditions: None,
+        })
+    }
+
+    /// Create a scratch buffer suitable for this set
+    ///
+    pub fn scratch(&self) -> Scratch {
+        let conditions = self.conditions.as_ref().map(|t| t.count).unwrap_or(0);
+        Scratch {
+            enabled: Vec::with_capacity(self.states.len()),
+            conditions: Vec::with_capacity(conditions),
+        }
+    }
+
+    /// Resolve the state for a given issue using a scratch buffer
+    ///
+    /// This function yields the same state as `Resolvable::issue_state_in()`
+    /// and fails for the same state and condition if a condition fails to
+    /// evaluate. No allocations are performed if the scratch buffer was created via
+    /// `scratch()` or was used with this set before.
+    ///
+    pub fn issue_state_with(
+        &self,
+        issue: &C::Issue,
+        context: &Context,
+        scratch: &mut Scratch
+    ) -> Result<Option<&Arc<IssueState<C>>>> {
+        let enabled = &mut scratch.enabled;
+        enabled.clear();
+
+        let conditions = &mut scratch.conditions;
+        conditions.clear();
+        if let Some(ref table) = self.conditions {
+            conditions.resize(table.count, None);
+        }
+
+        let mut retval = None;
+        for (index, state) in self.states.iter().enumerate() {
+            // The conditions are evaluated before the extended states are
+            // checked, like in the uncompiled resolution, such that failing
+            // conditions are reported for the same state.
+            let deps = &self.extends[self.offsets[index]..self.offsets[index + 1]];
+            let state_enabled = self.conditions_satisfied(index, issue, context, conditions)?
+                && deps.iter().all(|dep| enabled[*dep]);
+            enabled.push(state_enabled);
+            if state_enabled {
+                retval = Some(state);
+            }
+        }
+
+        Ok(retval)
+    }
+
+    /// Resolve the states for a sequence of issues
+    ///
+    /// The iterator returned yields the state of each issue, in order. A
+    /// single scratch buffer is used for all the issues. The conditions are
+    /// evaluated in a default `Context` created once for all the issues.
+    ///
+    pub fn issue_states<I>(&self, issues: I) -> IssueStates<'_, C, I::IntoIter>
+        where I: IntoIterator,
+              I::Item: Borrow<C::Issue>
+    {
+        self.issue_states_in(issues, &Context::default())
+    }
+
+    /// Resolve the states for a sequence of issues in a given context
+    ///
+    /// Like `issue_states()`, but the conditions are evaluated in the context
+    /// provided.
+    ///
+    pub fn issue_states_in<I>(&self, issues: I, context: &Context) -> IssueStates<'_, C, I::IntoIter>
+        where I: IntoIterator,
+              I::Item: Borrow<C::Issue>
+    {
+        IssueStates {
+            set: self,
+            issues: issues.into_iter(),
+            context: context.clone(),
+            scratch: self.scratch(),
+        }
+    }
+
+    /// Get an iterator for iterating over the issue states within the set
+    ///
+    /// This iterator will yield an issue state only after all its dependencies.
+    ///
+    pub fn iter(&self) -> ::std::slice::Iter<'_, Arc<IssueState<C>>> {
+        self.states.iter()
+    }
+
+    /// Check whether a state's conditions are satisfied
+    ///
+    /// If conditions are memoized, `results` is used for looking up and
+    /// recording the results of the distinct conditions.
+    ///
+    fn conditions_satisfied(
+        &self,
+        index: usize,
+        issue: &C::Issue,
+        context: &Context,
+        results: &mut [Option<bool>]
+    ) -> Result<bool> {
+        let table = match self.conditions {
+            Some(ref table) => table,
+            None => return self.states[index].try_conditions_satisfied(issue, context),
+        };
+
+        // Conditions are evaluated via the state at hand, such that failures
+        // are reported for that state rather than the one the distinct
+        // condition was first encountered in.
+        let ids = &table.ids[table.offsets[index]..table.offsets[index + 1]];
+        for (condition, id) in ids.iter().enumerate() {
+            let satisfied = match results[*id] {
+                Some(satisfied) => satisfied,
+                None => {
+                    let state = &self.states[index];
+                    let satisfied = state.try_condition_satisfied(condition, issue, context)?;
+                    results[*id] = Some(satisfied);
+                    satisfied
+                },
+            };
+            if !satisfied {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+
+impl<C> CompiledStateSet<C>
+    where C: Condition + Eq + Hash
+{
+    /// Compile a set of issue states, memoizing conditions
+    ///
+    /// Like `new()`, this function compiles the given set. Additionally,
+    /// identical conditions are detected, e.g. conditions comparing equal. For
+    /// any given issue, each of the distinct conditions will be evaluated at
+    /// most once, even if it appears in multiple states.
+    ///
+    pub fn new_memoized(states: &IssueStateSet<C>) -> Result<Self> {
+        let mut retval = Self::new(states)?;
+
+        let mut distinct = HashMap::new();
+        let mut ids = Vec::default();
+        let mut offsets = vec![0];
+
+        for state in retval.states.iter() {
+            for condition in state.conditions.iter() {
+                let next = distinct.len();
+                ids.push(*distinct.entry(condition).or_insert(next));
+            }
+            offsets.push(ids.len());
+        }
+
+        retval.conditions = Some(ConditionTable {
+            ids: ids.into_boxed_slice(),
+            offsets: offsets.into_boxed_slice(),
+            count: distinct.len(),
+        });
+        Ok(retval)
+    }
+}
+
+
+impl<C> Resolvable<C> for CompiledStateSet<C>
+    where C: Condition
+{
+    fn issue_state_in(
+        &self,
+        issue: &C::Issue,
+        context: &Context
+    ) -> Result<Option<Arc<IssueState<C>>>> {
+        self.issue_state_with(issue, context, &mut self.scratch()).map(|s| s.cloned())
+    }
+}
+
+
+
+
+/// Iterator over the states of a sequence of issues
+///
+/// Instances of this iterator are created via `CompiledStateSet::issue_states()`.
+///
+pub struct IssueStates<'a, C, I>
+    where C: Condition + 'a
+{
+    set: &'a CompiledStateSet<C>,
+    issues: I,
+    context: Context,
+    scratch: Scratch,
+}
+
+
+impl<'a, C, I> Iterator for IssueStates<'a, C, I>
+    where C: Condition + 'a,
+          I: Iterator,
+          I::Item: Borrow<C::Issue>
+{
+    type Item = Result<Option<&'a Arc<IssueState<C>>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let set = self.set;
+        let context = &self.context;
+        let scratch = &mut self.scratch;
+        self.issues.next().map(|issue| set.issue_state_with(issue.borrow(), context, scratch))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.issues.size_hint()
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections;
+    use std::error::Error as StdError;
+    use std::result::Result as RResult;
+    use test::{example_states, TestCond};
+
+    /// Condition failing if the issue lacks the flag
+    ///
+    #[derive(PartialEq, Eq, Hash)]
+    struct StrictCond(TestCond);
+
+    impl Condition for StrictCond {
+        type Issue = <TestCond as Condition>::Issue;
+
+        fn satisfied_by(&self, issue: &Self::Issue) -> bool {
+            self.0.satisfied_by(issue)
+        }
+
+        fn try_satisfied_by(&self, issue: &Self::Issue) -> RResult<bool, BoxError> {
+            let name = self.0.to_string();
+            issue
+                .get(name.as_str())
+                .cloned()
+                .ok_or_else(|| format!("no value for {}", name).into())
+        }
+    }
+
+    /// Generate issues with all combinations of the example's flags
+    ///
+    fn issues() -> Vec<collections::BTreeMap<&'static str, bool>> {
+        let flags = ["acked", "assigned", "closed"];
+        (0..(1 << flags.len()))
+            .map(|bits| flags
+                .iter()
+                .enumerate()
+                .map(|(i, flag)| (*flag, bits & (1 << i) != 0))
+                .collect())
+            .collect()
+    }
+
+    #[test]
+    fn equivalence() {
+        let states = example_states();
+        let compiled = CompiledStateSet::new(&states).expect("Failed to compile states.");
+        let mut scratch = compiled.scratch();
+
+        for issue in issues() {
+            let expected = states.issue_state(&issue).expect("Failed to determine state.");
+            assert_eq!(
+                compiled
+                    .issue_state_with(&issue, &Context::default(), &mut scratch)
+                    .expect("Failed to determine state.")
+                    .map(|s| s.name()),
+                expected.as_ref().map(|s| s.name())
+            );
+        }
+    }
+
+    #[test]
+    fn batch() {
+        let states = example_states();
+        let compiled = CompiledStateSet::new(&states).expect("Failed to compile states.");
+
+        let issues = issues();
+        let names: Vec<_> = compiled
+            .issue_states(issues.iter())
+            .map(|s| s.expect("Failed to determine state.").map(|s| s.name().as_str()))
+            .collect();
+        assert_eq!(names, vec![
+            Some("new"),
+            Some("acknowledged"),
+            Some("new"),
+            Some("assigned"),
+            Some("closed"),
+            Some("closed"),
+            Some("closed"),
+            Some("closed"),
+        ]);
+    }
+
+    #[test]
+    fn failing_conditions() {
+        let new : Arc<IssueState<StrictCond>> = IssueState::new("new".to_string()).into();
+        let acked : Arc<IssueState<StrictCond>> = {
+            let mut tmp = IssueState::new("acknowledged".to_string());
+            tmp.conditions = vec![StrictCond("acked".into())];
+            tmp.add_overridden(vec![new.clone()]);
+            tmp
+        }.into();
+        let assigned : Arc<IssueState<StrictCond>> = {
+            let mut tmp = IssueState::new("assigned".to_string());
+            tmp.conditions = vec![StrictCond("assigned".into())];
+            tmp.add_extended(vec![acked.clone()]);
+            tmp.add_overridden(vec![acked.clone()]);
+            tmp
+        }.into();
+        let states: IssueStateSet<_> = vec![new, acked, assigned].into();
+        let compiled = CompiledStateSet::new(&states).expect("Failed to compile states.");
+
+        // The extended state is disabled, but the condition is still evaluated
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("acked", false);
+
+        for err in [states.issue_state(&issue), compiled.issue_state(&issue)] {
+            let err = match err {
+                Ok(_) => panic!("Failed to report failure."),
+                Err(err) => err,
+            };
+            match *err.kind() {
+                ErrorKind::ConditionFailed {ref state, condition} => {
+                    assert_eq!(state, "assigned");
+                    assert_eq!(condition, 0);
+                },
+                _ => panic!("Unexpected error: {}", err),
+            }
+            assert_eq!(err.source().expect("Missing source.").to_string(), "no value for assigned");
+        }
+    }
+
+    #[test]
+    fn memoized() {
+        use std::cell::Cell;
+        use std::hash::Hasher;
+        use std::rc::Rc;
+
+        struct CountingCond(TestCond, Rc<Cell<usize>>);
+
+        impl PartialEq for CountingCond {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl Eq for CountingCond {}
+
+        impl Hash for CountingCond {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.to_string().hash(state)
+            }
+        }
+
+        impl Condition for CountingCond {
+            type Issue = <TestCond as Condition>::Issue;
+
+            fn satisfied_by(&self, issue: &Self::Issue) -> bool {
+                self.1.set(self.1.get() + 1);
+                self.0.satisfied_by(issue)
+            }
+        }
+
+        let count = Rc::new(Cell::new(0));
+        let cond = |name: &'static str| CountingCond(name.into(), count.clone());
+
+        let open : Arc<IssueState<CountingCond>> = {
+            let mut tmp = IssueState::new("open".to_string());
+            tmp.conditions = vec![cond("confirmed")];
+            tmp
+        }.into();
+        let assigned : Arc<IssueState<CountingCond>> = {
+            let mut tmp = IssueState::new("assigned".to_string());
+            tmp.conditions = vec![cond("confirmed"), cond("assigned")];
+            tmp.add_overridden(vec![open.clone()]);
+            tmp
+        }.into();
+        let scheduled : Arc<IssueState<CountingCond>> = {
+            let mut tmp = IssueState::new("scheduled".to_string());
+            tmp.conditions = vec![cond("assigned"), cond("milestone")];
+            tmp.add_overridden(vec![assigned.clone()]);
+            tmp
+        }.into();
+        let states: IssueStateSet<_> = vec![open, assigned, scheduled].into();
+
+        let compiled = CompiledStateSet::new_memoized(&states).expect("Failed to compile states.");
+        let mut scratch = compiled.scratch();
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("confirmed", true);
+        issue.insert("assigned", true);
+        issue.insert("milestone", false);
+
+        for _ in 0..2 {
+            count.set(0);
+            let state = compiled
+                .issue_state_with(&issue, &Context::default(), &mut scratch)
+                .expect("Failed to determine state.")
+                .expect("Wrongly determined no state.");
+            assert_eq!(state.name(), "assigned");
+            assert_eq!(count.get(), 3);
+        }
+
+        let unmemoized = CompiledStateSet::new(&states).expect("Failed to compile states.");
+        count.set(0);
+        unmemoized.issue_state(&issue).expect("Failed to determine state.");
+        assert_eq!(count.get(), 5);
+    }
+
+    #[test]
+    fn memoized_failing_conditions() {
+        let open : Arc<IssueState<StrictCond>> = {
+            let mut tmp = IssueState::new("open".to_string());
+            tmp.conditions = vec![StrictCond("confirmed".into()), StrictCond("assigned".into())];
+            tmp
+        }.into();
+        let assigned : Arc<IssueState<StrictCond>> = {
+            let mut tmp = IssueState::new("assigned".to_string());
+            tmp.conditions = vec![StrictCond("assigned".into())];
+            tmp.add_overridden(vec![open.clone()]);
+            tmp
+        }.into();
+        let states: IssueStateSet<_> = vec![open, assigned].into();
+        let compiled = CompiledStateSet::new_memoized(&states).expect("Failed to compile states.");
+
+        // The shared condition is first evaluated for the second state
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("confirmed", false);
+
+        let err = match compiled.issue_state(&issue) {
+            Ok(_) => panic!("Failed to report failure."),
+            Err(err) => err,
+        };
+        match *err.kind() {
+            ErrorKind::ConditionFailed {ref state, condition} => {
+                assert_eq!(state, "assigned");
+                assert_eq!(condition, 0);
+            },
+            _ => panic!("Unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn memoized_metadata() {
+        use condition::ConditionFactory;
+        use metadata::{MetadataCondition, MetadataConditionFactory, Value};
+
+        type Metadata = collections::BTreeMap<&'static str, Value<'static>>;
+
+        let factory = MetadataConditionFactory::default();
+        let cond = |atom: &str| -> MetadataCondition<Metadata> {
+            factory.parse_condition(atom).expect("Failed to parse condition.")
+        };
+
+        let open : Arc<IssueState<_>> = {
+            let mut tmp = IssueState::new("open".to_string());
+            tmp.conditions = vec![cond("priority>=2")];
+            tmp
+        }.into();
+        let assigned : Arc<IssueState<_>> = {
+            let mut tmp = IssueState::new("assigned".to_string());
+            tmp.conditions = vec![cond("priority>=2"), cond("assignee")];
+            tmp.add_overridden(vec![open.clone()]);
+            tmp
+        }.into();
+        let states: IssueStateSet<_> = vec![open, assigned].into();
+        let compiled = CompiledStateSet::new_memoized(&states).expect("Failed to compile states.");
+
+        let mut issue = Metadata::new();
+        issue.insert("priority", Value::Integer(3));
+        issue.insert("assignee", Value::String("jdoe"));
+        let state = compiled
+            .issue_state(&issue)
+            .expect("Failed to determine state.")
+            .expect("Wrongly determined no state.");
+        assert_eq!(state.name(), "assigned");
+    }
+}
@@ -29,9 +29,12 @@
 //! by the library's user.
 //!
 
+use std::borrow::Cow;
 use std::error::Error as EError;
+use std::fmt;
 use std::result::Result as RResult;
 
+use context::Context;
 use error::*;
 
 
@@ -60,6 +63,107 @@ pub trait Condition {
     /// Check whether the condition is satisfied by the issue provided
     ///
     fn satisfied_by(&self, issue: &Self::Issue) -> bool;
+
+    /// Check whether the condition is satisfied, reporting failures
+    ///
+    /// Conditions whose evaluation may fail, e.g. because of malformed
+    /// metadata, should implement this function and report such failures as
+    /// errors rather than considering the condition unsatisfied. The resolution
+    /// of issue states is performed using this function.
+    ///
+    /// The default implementation defers to `satisfied_by()`.
+    ///
+    fn try_satisfied_by(&self, issue: &Self::Issue) -> RResult<bool, BoxError> {
+        Ok(self.satisfied_by(issue))
+    }
+
+    /// Check whether the condition is satisfied in a given context
+    ///
+    /// Conditions depending on the environment, e.g. the current time, should
+    /// implement this function and retrieve the information from the context
+    /// rather than from the environment directly. The resolution of issue
+    /// states is performed using this function.
+    ///
+    /// The default implementation defers to `try_satisfied_by()`.
+    ///
+    fn try_satisfied_in(
+        &self,
+        issue: &Self::Issue,
+        context: &Context
+    ) -> RResult<bool, BoxError> {
+        let _ = context;
+        self.try_satisfied_by(issue)
+    }
+
+    /// Check whether the condition is satisfied by a partially known issue
+    ///
+    /// If the information required for deciding whether the condition is
+    /// satisfied is not (yet) available, e.g. because the issue's metadata is
+    /// loaded lazily, this function yields `None`.
+    ///
+    /// The default implementation defers to `satisfied_by()`, e.g. assumes that
+    /// all the information is available.
+    ///
+    fn satisfied_by_partial(&self, issue: &Self::Issue) -> Option<bool> {
+        Some(self.satisfied_by(issue))
+    }
+
+    /// Check whether the condition is satisfied by a partially known issue in a given context
+    ///
+    /// Like `try_satisfied_in()`, this function allows conditions depending on
+    /// the environment to retrieve the information from the context. The
+    /// resolution of states for partially known issues is performed using this
+    /// function.
+    ///
+    /// The default implementation defers to `satisfied_by_partial()`.
+    ///
+    fn satisfied_by_partial_in(&self, issue: &Self::Issue, context: &Context) -> Option<bool> {
+        let _ = context;
+        self.satisfied_by_partial(issue)
+    }
+}
+
+
+/// Trait for conditions representing a single condition atom
+///
+/// Implementing this trait exposes the components of the atom the condition
+/// was created from, which allows analysing issue states statically.
+///
+pub trait ConditionAtom {
+    /// Retrieve the metadata identifier
+    ///
+    fn name(&self) -> &str;
+
+    /// Check whether the atom is negated
+    ///
+    fn negated(&self) -> bool;
+
+    /// Retrieve the match operator and value, if any
+    ///
+    fn op_val(&self) -> Option<(&MatchOp, &str)>;
+}
+
+
+
+
+/// Compute the conjunction of partial results
+///
+/// Following Kleene's three-valued logic, the conjunction is false if any of
+/// the values is false, unknown (`None`) if any of the values is unknown and
+/// true otherwise. The evaluation stops at the first false value.
+///
+pub fn all_partial<I>(values: I) -> Option<bool>
+    where I: IntoIterator<Item = Option<bool>>
+{
+    let mut retval = Some(true);
+    for value in values {
+        match value {
+            Some(true) => (),
+            Some(false) => return Some(false),
+            None => retval = None,
+        }
+    }
+    retval
 }
 
 
@@ -72,7 +176,7 @@ pub trait Condition {
 /// considered the "left-hand value" while the latter is considered the
 /// "right-hand value" in this context.
 ///
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum MatchOp {
     /// Match if the values are evivalent
     Equivalence,
@@ -89,6 +193,28 @@ pub enum MatchOp {
     /// Match if the left-hand value contains or is equal to the right-hand
     /// value.
     Contains,
+    /// Match if the left-hand value matches the right-hand value interpreted
+    /// as a regular expression.
+    Regex,
+    /// Match if the left-hand value matches the right-hand value interpreted
+    /// as a glob pattern.
+    Glob,
+}
+
+
+impl fmt::Display for MatchOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            MatchOp::Equivalence        => "=",
+            MatchOp::LowerThan          => "<",
+            MatchOp::GreaterThan        => ">",
+            MatchOp::LowerThanOrEqual   => "<=",
+            MatchOp::GreaterThanOrEqual => ">=",
+            MatchOp::Contains           => "~",
+            MatchOp::Regex              => "=~",
+            MatchOp::Glob               => "*=",
+        })
+    }
 }
 
 
@@ -101,9 +227,7 @@ pub enum MatchOp {
 /// the function `make_condition()` will be supplied with the components of a
 /// condition.
 ///
-pub trait ConditionFactory<C>
-    where C: Condition + Sized
-{
+pub trait ConditionFactory<C> {
     type Error : From<Error> + EError;
 
     /// Create a condition from bits and pieces
@@ -133,15 +257,63 @@ pub trait ConditionFactory<C>
         &self,
         string: &str,
     ) -> RResult<C, Self::Error> {
-        parse_condition(string)
-            .map_err(From::from)
-            .and_then(|(name, neg, op_val)| self.make_condition(name, neg, op_val))
+        let (name, neg, op_val) = parse_condition(string)?;
+        let op_val = op_val.as_ref().map(|(op, val)| (op.clone(), val.as_ref()));
+        self.make_condition(name.as_ref(), neg, op_val)
+    }
+
+    /// Create the conjunction of a list of conditions
+    ///
+    /// Factories for condition types which support composition should
+    /// implement this function. By default, an error is returned.
+    ///
+    fn make_conjunction(
+        &self,
+        _conditions: Vec<C>
+    ) -> RResult<C, Self::Error> {
+        Err(Error::from(ErrorKind::CompositionUnsupported).into())
+    }
+
+    /// Create the disjunction of a list of conditions
+    ///
+    /// Factories for condition types which support composition should
+    /// implement this function. By default, an error is returned.
+    ///
+    fn make_disjunction(
+        &self,
+        _conditions: Vec<C>
+    ) -> RResult<C, Self::Error> {
+        Err(Error::from(ErrorKind::CompositionUnsupported).into())
+    }
+
+    /// Create the negation of a condition
+    ///
+    /// Factories for condition types which support composition should
+    /// implement this function. By default, an error is returned.
+    ///
+    fn make_negation(
+        &self,
+        _condition: C
+    ) -> RResult<C, Self::Error> {
+        Err(Error::from(ErrorKind::CompositionUnsupported).into())
     }
 }
 
 
 
 
+/// Bits of a condition atom
+///
+/// The "metadata identifier", a flag indicating whether the condition is
+/// negated and, optionally, the matching operator and the string
+/// representation of the right-hand side value.
+///
+/// The identifier and the value are borrowed from the atom's string
+/// representation unless they are quoted.
+///
+pub type AtomParts<'a> = (Cow<'a, str>, bool, Option<(MatchOp, Cow<'a, str>)>);
+
+
 /// Parse the bits of a condition atom
 ///
 /// This method parses a condition atom. It returns the "metadata identifier"
@@ -152,30 +324,81 @@ pub trait ConditionFactory<C>
 /// The matching operator and value may be `None`. In this case, the condition
 /// parsed is expected to check for the existence of a piece of metadata.
 ///
-pub fn parse_condition(string: &str) -> Result<(&str, bool, Option<(MatchOp, &str)>)> {
-    if let Some(pos) = string.find(|ref c| reserved_char(c)) {
-        if pos == 0 {
-            // The condition is either a negated existance (e.g. starts with
-            // `!`) or invalid.
-            let (neg, name) = string.split_at(1);
-            return if neg == "!" && !name.contains(|ref c| reserved_char(c)) {
-                Ok((name, true, None))
-            } else {
-                Err(Error::from(ErrorKind::ConditionParseError))
-            }
-        }
+/// Both the identifier and the value may be enclosed in double quotes, in
+/// which case a backslash escapes the character following it. Quoted
+/// identifiers may contain reserved characters.
+///
+pub fn parse_condition(string: &str) -> Result<AtomParts<'_>> {
+    // A leading `!` denotes a negated existance
+    let (negated_existance, string) = match string.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, string),
+    };
+
+    let (name, op_val) = parse_identifier(string)?;
+    if op_val.is_empty() {
+        return Ok((name, negated_existance, None));
+    }
+    if negated_existance {
+        return Err(Error::from(ErrorKind::ConditionParseError));
+    }
 
-        let (name, mut op_val) = string.split_at(pos);
-        let negated = op_val.starts_with('!');
-        if negated {
-            op_val = op_val.split_at(1).1;
+    let (negated, op_val) = match op_val.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, op_val),
+    };
+    let (op, val) = parse_op_val(op_val)?;
+    let val = if val.starts_with('"') {
+        match parse_quoted(val)? {
+            (val, "") => val,
+            _ => return Err(Error::from(ErrorKind::ConditionParseError)),
         }
-        Ok((name, negated, parse_op_val(op_val)?.into()))
     } else {
-        // If the string representation does not contain any reserved
-        // characters, this condition is the existance of the piece of metadata.
-        Ok((string, false, None))
+        Cow::Borrowed(val)
+    };
+
+    Ok((name, negated, Some((op, val))))
+}
+
+
+/// Write the string representation of a condition atom
+///
+/// This function is the counterpart of `parse_condition()`: it writes an atom
+/// which will be parsed into the bits provided. The identifier and the value
+/// are quoted if necessary.
+///
+pub fn write_atom<W>(
+    w: &mut W,
+    name: &str,
+    negated: bool,
+    op_val: Option<(&MatchOp, &str)>
+) -> fmt::Result
+    where W: fmt::Write
+{
+    let neg = if negated { "!" } else { "" };
+    let quote_name = name.is_empty()
+        || name.starts_with('"')
+        || name.ends_with('*')
+        || name.contains(|ref c| reserved_char(c));
+
+    if op_val.is_none() {
+        w.write_str(neg)?;
+    }
+    if quote_name {
+        write_quoted(w, name)?;
+    } else {
+        w.write_str(name)?;
+    }
+
+    if let Some((op, val)) = op_val {
+        write!(w, "{}{}", neg, op)?;
+        if val.starts_with(['"', '=', '~']) {
+            write_quoted(w, val)?;
+        } else {
+            w.write_str(val)?;
+        }
     }
+    Ok(())
 }
 
 
@@ -186,13 +409,80 @@ fn reserved_char(c: &char) -> bool {
 }
 
 
+/// Parse and extract the metadata identifier from the start of an atom
+///
+/// The identifier and the remainder of the string are returned.
+///
+fn parse_identifier(string: &str) -> Result<(Cow<'_, str>, &str)> {
+    if string.starts_with('"') {
+        return parse_quoted(string);
+    }
+
+    let mut pos = string.find(|ref c| reserved_char(c)).unwrap_or(string.len());
+
+    // The `*` of a glob operator is not a reserved character on its own
+    if string.split_at(pos).1.starts_with('=') && string.split_at(pos).0.ends_with('*') {
+        pos -= 1;
+    }
+
+    if pos == 0 {
+        return Err(Error::from(ErrorKind::ConditionParseError));
+    }
+    let (name, rest) = string.split_at(pos);
+    Ok((Cow::Borrowed(name), rest))
+}
+
+
+/// Parse a quoted string from the start of a string
+///
+/// The unescaped contents of the quoted string and the remainder of the string
+/// following the closing quote are returned.
+///
+fn parse_quoted(string: &str) -> Result<(Cow<'_, str>, &str)> {
+    let mut value = String::new();
+    let mut chars = string.char_indices().skip(1);
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            '"' => return Ok((Cow::Owned(value), string.split_at(pos + 1).1)),
+            '\\' => match chars.next() {
+                Some((_, c)) => value.push(c),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+
+    // The closing quote is missing
+    Err(Error::from(ErrorKind::ConditionParseError))
+}
+
+
+/// Write a string as a quoted string
+///
+fn write_quoted<W>(w: &mut W, string: &str) -> fmt::Result
+    where W: fmt::Write
+{
+    w.write_char('"')?;
+    for c in string.chars() {
+        if c == '"' || c == '\\' {
+            w.write_char('\\')?;
+        }
+        w.write_char(c)?;
+    }
+    w.write_char('"')
+}
+
+
 /// Parse and extract the match operator and value from the compound
 ///
 fn parse_op_val(string: &str) -> Result<(MatchOp, &str)> {
     let mut chars = string.chars();
 
     let (op, pos) = match chars.next() {
-        Some('=') => (MatchOp::Equivalence, 1),
+        Some('=') => match chars.next() {
+            Some('~') => (MatchOp::Regex, 2),
+            _ => (MatchOp::Equivalence, 1),
+        },
         Some('<') => match chars.next() {
             Some('=') => (MatchOp::LowerThanOrEqual, 2),
             _ => (MatchOp::LowerThan, 1),
@@ -202,6 +492,10 @@ fn parse_op_val(string: &str) -> Result<(MatchOp, &str)> {
             _ => (MatchOp::GreaterThan, 1),
         },
         Some('~') => (MatchOp::Contains, 1),
+        Some('*') => match chars.next() {
+            Some('=') => (MatchOp::Glob, 2),
+            _ => return Err(Error::from(ErrorKind::ConditionParseError)),
+        },
         _ => return Err(Error::from(ErrorKind::ConditionParseError)),
     };
 
@@ -215,25 +509,99 @@ fn parse_op_val(string: &str) -> Result<(MatchOp, &str)> {
 mod tests {
     use super::*;
 
-    fn parse(string: &str) -> (&str, bool, Option<(MatchOp, &str)>) {
+    fn parse(string: &str) -> AtomParts<'_> {
         parse_condition(string).expect("Failed to parse condition atom!")
     }
 
+    #[test]
+    fn relative_time() {
+        assert_eq!(
+            parse("updated<now-30d"),
+            ("updated".into(), false, Some((MatchOp::LowerThan, "now-30d".into())))
+        );
+        assert_eq!(
+            parse("due!<=now+P1W"),
+            ("due".into(), true, Some((MatchOp::LowerThanOrEqual, "now+P1W".into())))
+        );
+    }
+
     #[test]
     fn smoke() {
-        assert_eq!(parse("foo"), ("foo", false, None));
-        assert_eq!(parse("!foo"), ("foo", true, None));
-        assert_eq!(parse("foo=bar"), ("foo", false, Some((MatchOp::Equivalence, "bar"))));
-        assert_eq!(parse("foo<bar"), ("foo", false, Some((MatchOp::LowerThan, "bar"))));
-        assert_eq!(parse("foo>bar"), ("foo", false, Some((MatchOp::GreaterThan, "bar"))));
-        assert_eq!(parse("foo<=bar"), ("foo", false, Some((MatchOp::LowerThanOrEqual, "bar"))));
-        assert_eq!(parse("foo>=bar"), ("foo", false, Some((MatchOp::GreaterThanOrEqual, "bar"))));
-        assert_eq!(parse("foo!~bar"), ("foo", true, Some((MatchOp::Contains, "bar"))));
-        assert_eq!(parse("foo!=bar"), ("foo", true, Some((MatchOp::Equivalence, "bar"))));
-        assert_eq!(parse("foo!<bar"), ("foo", true, Some((MatchOp::LowerThan, "bar"))));
-        assert_eq!(parse("foo!>bar"), ("foo", true, Some((MatchOp::GreaterThan, "bar"))));
-        assert_eq!(parse("foo!<=bar"), ("foo", true, Some((MatchOp::LowerThanOrEqual, "bar"))));
-        assert_eq!(parse("foo!>=bar"), ("foo", true, Some((MatchOp::GreaterThanOrEqual, "bar"))));
-        assert_eq!(parse("foo!~bar"), ("foo", true, Some((MatchOp::Contains, "bar"))));
+        assert_eq!(parse("foo"), ("foo".into(), false, None));
+        assert_eq!(parse("!foo"), ("foo".into(), true, None));
+        assert_eq!(parse("foo=bar"), ("foo".into(), false, Some((MatchOp::Equivalence, "bar".into()))));
+        assert_eq!(parse("foo<bar"), ("foo".into(), false, Some((MatchOp::LowerThan, "bar".into()))));
+        assert_eq!(parse("foo>bar"), ("foo".into(), false, Some((MatchOp::GreaterThan, "bar".into()))));
+        assert_eq!(parse("foo<=bar"), ("foo".into(), false, Some((MatchOp::LowerThanOrEqual, "bar".into()))));
+        assert_eq!(parse("foo>=bar"), ("foo".into(), false, Some((MatchOp::GreaterThanOrEqual, "bar".into()))));
+        assert_eq!(parse("foo!~bar"), ("foo".into(), true, Some((MatchOp::Contains, "bar".into()))));
+        assert_eq!(parse("foo!=bar"), ("foo".into(), true, Some((MatchOp::Equivalence, "bar".into()))));
+        assert_eq!(parse("foo!<bar"), ("foo".into(), true, Some((MatchOp::LowerThan, "bar".into()))));
+        assert_eq!(parse("foo!>bar"), ("foo".into(), true, Some((MatchOp::GreaterThan, "bar".into()))));
+        assert_eq!(parse("foo!<=bar"), ("foo".into(), true, Some((MatchOp::LowerThanOrEqual, "bar".into()))));
+        assert_eq!(parse("foo!>=bar"), ("foo".into(), true, Some((MatchOp::GreaterThanOrEqual, "bar".into()))));
+        assert_eq!(parse("foo!~bar"), ("foo".into(), true, Some((MatchOp::Contains, "bar".into()))));
+        assert_eq!(parse("foo=~bar"), ("foo".into(), false, Some((MatchOp::Regex, "bar".into()))));
+        assert_eq!(parse("foo!=~bar"), ("foo".into(), true, Some((MatchOp::Regex, "bar".into()))));
+        assert_eq!(parse("foo*=bar"), ("foo".into(), false, Some((MatchOp::Glob, "bar".into()))));
+        assert_eq!(parse("foo!*=bar"), ("foo".into(), true, Some((MatchOp::Glob, "bar".into()))));
+        assert_eq!(parse("foo*"), ("foo*".into(), false, None));
+    }
+
+    #[test]
+    fn quoted() {
+        assert_eq!(parse("\"foo=bar\""), ("foo=bar".into(), false, None));
+        assert_eq!(parse("!\"foo bar\""), ("foo bar".into(), true, None));
+        assert_eq!(
+            parse("status=\"needs review\""),
+            ("status".into(), false, Some((MatchOp::Equivalence, "needs review".into())))
+        );
+        assert_eq!(
+            parse("\"a<b\"!<=\"=x\""),
+            ("a<b".into(), true, Some((MatchOp::LowerThanOrEqual, "=x".into())))
+        );
+        assert_eq!(
+            parse("foo=\"say \\\"hi\\\" \\\\o/\""),
+            ("foo".into(), false, Some((MatchOp::Equivalence, "say \"hi\" \\o/".into())))
+        );
+
+        // Unquoted values are taken verbatim
+        assert_eq!(
+            parse("foo=a \"b\""),
+            ("foo".into(), false, Some((MatchOp::Equivalence, "a \"b\"".into())))
+        );
+        assert_eq!(parse("foo==x"), ("foo".into(), false, Some((MatchOp::Equivalence, "=x".into()))));
+    }
+
+    #[test]
+    fn invalid() {
+        for atom in &["", "!", "=foo", "!foo=bar", "foo!", "foo!*bar", "\"foo", "foo=\"bar", "foo=\"bar\"baz"] {
+            assert!(parse_condition(atom).is_err(), "Parsed invalid atom {}", atom);
+        }
+    }
+
+    #[test]
+    fn write() {
+        let atoms = vec![
+            ("foo", false, None),
+            ("foo", true, None),
+            ("foo=bar", true, None),
+            ("foo", true, Some((MatchOp::LowerThan, "bar"))),
+            ("status", false, Some((MatchOp::Equivalence, "needs review"))),
+            ("a<b", false, Some((MatchOp::LowerThanOrEqual, "=x"))),
+            ("foo", false, Some((MatchOp::Contains, "\"hi\" \\o/"))),
+            ("foo*", false, None),
+            ("foo*", false, Some((MatchOp::Equivalence, "~bar"))),
+            ("foo", true, Some((MatchOp::Glob, "*bar"))),
+        ];
+        for (name, negated, op_val) in atoms {
+            let mut atom = String::new();
+            write_atom(&mut atom, name, negated, op_val.as_ref().map(|&(ref op, val)| (op, val)))
+                .expect("Failed to write atom");
+            let (parsed_name, parsed_negated, parsed_op_val) = parse(atom.as_str());
+            assert_eq!(parsed_name, name);
+            assert_eq!(parsed_negated, negated);
+            assert_eq!(parsed_op_val.as_ref().map(|(op, val)| (op.clone(), val.as_ref())), op_val);
+        }
     }
 }
@@ -0,0 +1,107 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Evaluation context
+//!
+//! This module provides the `Context` in which conditions are evaluated. The
+//! context carries the current time, which allows conditions depending on the
+//! time to be evaluated deterministically, as well as arbitrary named values
+//! describing the environment, e.g. the user viewing an issue.
+//!
+
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+
+
+
+/// Context for the evaluation of conditions
+///
+/// The context is passed to conditions via `Condition::try_satisfied_in()`.
+///
+#[derive(Debug, Clone)]
+pub struct Context {
+    now: SystemTime,
+    values: BTreeMap<String, String>,
+}
+
+
+impl Context {
+    /// Create a context with a given current time
+    ///
+    pub fn new(now: SystemTime) -> Self {
+        Self {now, values: BTreeMap::new()}
+    }
+
+    /// Retrieve the current time
+    ///
+    pub fn now(&self) -> SystemTime {
+        self.now
+    }
+
+    /// Add a named value to the context
+    ///
+    pub fn with_value<K, V>(mut self, name: K, value: V) -> Self
+        where K: Into<String>,
+              V: Into<String>
+    {
+        self.values.insert(name.into(), value.into());
+        self
+    }
+
+    /// Retrieve a named value
+    ///
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+}
+
+
+/// Create a context for the current time, as reported by the system clock
+///
+impl Default for Context {
+    fn default() -> Self {
+        Self::new(SystemTime::now())
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn values() {
+        let now = UNIX_EPOCH + Duration::from_secs(1000);
+        let context = Context::new(now).with_value("user", "jdoe");
+
+        assert_eq!(context.now(), now);
+        assert_eq!(context.get("user"), Some("jdoe"));
+        assert_eq!(context.get("team"), None);
+    }
+}
@@ -0,0 +1,461 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Dates, timestamps and durations
+//!
+//! This module provides parsers for the textual representation of points in
+//! time and durations used in the values of condition atoms:
+//! * timestamps are represented as ISO-8601 dates (`2026-12-01`) or date-times
+//!   (`2026-12-01T12:00:00Z`, `2026-12-01T12:00+02:00`), interpreted as UTC
+//!   unless an offset is given,
+//! * durations are represented either as ISO-8601 durations (`P14D`,
+//!   `PT1H30M`) or as a sequence of numbers with units (`14d`, `1h30m`), the
+//!   units being `w`, `d`, `h`, `m` and `s` and
+//! * points in time relative to the current time are represented as `now`,
+//!   optionally followed by `+` or `-` and a duration, e.g. `now-14d`.
+//!
+//! Calendar-dependent durations, e.g. months and years, are not supported.
+//! Timestamps are formatted as ISO-8601 date-times in UTC.
+//!
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+
+
+
+/// Point in time, either absolute or relative to the current time
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TimeLiteral {
+    /// A fixed point in time
+    Absolute(SystemTime),
+    /// A point in time relative to the current time
+    Relative {
+        /// Distance from the current time
+        offset: Duration,
+        /// Whether the point in time lies in the past
+        past: bool,
+    },
+}
+
+
+impl TimeLiteral {
+    /// Parse a point in time
+    ///
+    /// Both timestamps and relative points in time are accepted.
+    ///
+    pub fn parse(string: &str) -> Option<Self> {
+        let rest = match string.strip_prefix("now") {
+            Some(rest) => rest,
+            None => return parse_timestamp(string).map(TimeLiteral::Absolute),
+        };
+
+        let (past, offset) = match rest.chars().next() {
+            None => return Some(TimeLiteral::Relative {offset: Duration::from_secs(0), past: false}),
+            Some('+') => (false, &rest[1..]),
+            Some('-') => (true, &rest[1..]),
+            _ => return None,
+        };
+        parse_duration(offset).map(|offset| TimeLiteral::Relative {offset, past})
+    }
+
+    /// Compute the point in time, given the current time
+    ///
+    /// If the point in time can not be represented, e.g. because the offset
+    /// is too large, this function yields `None`.
+    ///
+    pub fn at(&self, now: SystemTime) -> Option<SystemTime> {
+        match *self {
+            TimeLiteral::Absolute(time) => Some(time),
+            TimeLiteral::Relative {offset, past: true} => now.checked_sub(offset),
+            TimeLiteral::Relative {offset, past: false} => now.checked_add(offset),
+        }
+    }
+}
+
+
+
+
+/// Parse an ISO-8601 date or date-time
+///
+/// A date is interpreted as midnight (UTC) of that day. The time may include
+/// seconds and fractions of a second. The date and the time may be separated by
+/// either `T` or a space. Date-times without an offset are interpreted as UTC.
+///
+pub fn parse_timestamp(string: &str) -> Option<SystemTime> {
+    let (date, time) = match string.find(['T', ' ']) {
+        Some(pos) => (&string[..pos], Some(&string[pos + 1..])),
+        None => (string, None),
+    };
+
+    let mut secs = parse_date(date)? * 86400;
+    if let Some(time) = time {
+        let (time, offset) = split_offset(time)?;
+        let (time, nanos) = match time.find('.') {
+            Some(pos) => (&time[..pos], parse_fraction(&time[pos + 1..])?),
+            None => (time, 0),
+        };
+
+        // Fractions are only permitted for seconds
+        let parts = time.split(':').map(|n| parse_fixed(n, 2)).collect::<Option<Vec<_>>>()?;
+        let (hours, minutes, seconds) = match parts[..] {
+            [hours, minutes] if nanos == 0 => (hours, minutes, 0),
+            [hours, minutes, seconds] => (hours, minutes, seconds),
+            _ => return None,
+        };
+        if hours > 23 || minutes > 59 || seconds > 60 {
+            return None;
+        }
+
+        secs += (hours * 60 + minutes) * 60 + seconds - offset;
+        return epoch_offset(secs)?.checked_add(Duration::from_nanos(nanos));
+    }
+
+    epoch_offset(secs)
+}
+
+
+/// Format a point in time as an ISO-8601 date-time in UTC
+///
+/// Fractions of a second are only included if present. The result is accepted
+/// by `parse_timestamp`.
+///
+pub fn format_timestamp(time: SystemTime) -> String {
+    let (secs, nanos) = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => (since.as_secs() as i64, since.subsec_nanos()),
+        Err(err) => {
+            let before = err.duration();
+            match before.subsec_nanos() {
+                0 => (-(before.as_secs() as i64), 0),
+                n => (-(before.as_secs() as i64) - 1, 1_000_000_000 - n),
+            }
+        },
+    };
+
+    let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+    let secs = secs.rem_euclid(86400);
+    let mut retval = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+    );
+    if nanos != 0 {
+        retval.push_str(format!(".{:09}", nanos).trim_end_matches('0'));
+    }
+    retval.push('Z');
+    retval
+}
+
+
+/// Parse a duration
+///
+/// Both ISO-8601 durations and sequences of numbers with units are accepted.
+///
+pub fn parse_duration(string: &str) -> Option<Duration> {
+    if let Some(iso) = string.strip_prefix('P') {
+        let (date, time) = match iso.find('T') {
+            Some(pos) => (&iso[..pos], Some(&iso[pos + 1..])),
+            None => (iso, None),
+        };
+        if date.is_empty() && time.is_none_or(str::is_empty) {
+            return None;
+        }
+        let mut secs = parse_units(date, &[('W', 604800), ('D', 86400)])?;
+        if let Some(time) = time {
+            if time.is_empty() {
+                return None;
+            }
+            secs += parse_units(time, &[('H', 3600), ('M', 60), ('S', 1)])?;
+        }
+        return Some(Duration::from_secs(secs));
+    }
+
+    if string.is_empty() {
+        return None;
+    }
+    parse_units(string, &[('w', 604800), ('d', 86400), ('h', 3600), ('m', 60), ('s', 1)])
+        .map(Duration::from_secs)
+}
+
+
+/// Parse a sequence of numbers followed by units
+///
+/// Each unit is given together with its length in seconds. The units must
+/// appear in the order given, each at most once. The total number of seconds is
+/// returned.
+///
+fn parse_units(string: &str, units: &[(char, u64)]) -> Option<u64> {
+    let mut units = units.iter();
+    let mut secs: u64 = 0;
+    let mut rest = string;
+    while !rest.is_empty() {
+        let pos = rest.find(|c: char| !c.is_ascii_digit())?;
+        if pos == 0 {
+            return None;
+        }
+        let number: u64 = rest[..pos].parse().ok()?;
+        let unit = rest[pos..].chars().next()?;
+        let length = units.find(|u| u.0 == unit)?.1;
+        secs = secs.checked_add(number.checked_mul(length)?)?;
+        rest = &rest[pos + unit.len_utf8()..];
+    }
+    Some(secs)
+}
+
+
+/// Parse a date in the form `YYYY-MM-DD`
+///
+/// The number of days since the Unix epoch is returned.
+///
+fn parse_date(string: &str) -> Option<i64> {
+    let mut parts = string.split('-');
+    let year = parse_fixed(parts.next()?, 4)?;
+    let month = parse_fixed(parts.next()?, 2)?;
+    let day = parse_fixed(parts.next()?, 2)?;
+    if parts.next().is_some() || !(1..=12).contains(&month) {
+        return None;
+    }
+    if !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+
+/// Split a time into the time proper and the offset from UTC in seconds
+///
+fn split_offset(time: &str) -> Option<(&str, i64)> {
+    if let Some(time) = time.strip_suffix('Z') {
+        return Some((time, 0));
+    }
+
+    let pos = match time.rfind(['+', '-']) {
+        Some(pos) => pos,
+        None => return Some((time, 0)),
+    };
+    let (time, offset) = time.split_at(pos);
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let offset = &offset[1..];
+    let (hours, minutes) = match offset.find(':') {
+        Some(pos) => (&offset[..pos], &offset[pos + 1..]),
+        None if offset.len() == 4 => offset.split_at(2),
+        None => (offset, "00"),
+    };
+    let hours = parse_fixed(hours, 2)?;
+    let minutes = parse_fixed(minutes, 2)?;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some((time, sign * (hours * 60 + minutes) * 60))
+}
+
+
+/// Parse a number consisting of exactly the given number of digits
+///
+fn parse_fixed(string: &str, digits: usize) -> Option<i64> {
+    if string.len() != digits || !string.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    string.parse().ok()
+}
+
+
+/// Parse the fraction of a second, yielding nanoseconds
+///
+fn parse_fraction(string: &str) -> Option<u64> {
+    if string.is_empty() || !string.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // Digits beyond nanosecond precision are ignored
+    let digits = &string[..string.len().min(9)];
+    let nanos: u64 = digits.parse().ok()?;
+    Some(nanos * 10u64.pow(9 - digits.len() as u32))
+}
+
+
+/// Compute the point in time a given number of seconds after the Unix epoch
+///
+/// If the point in time can not be represented, this function yields `None`.
+///
+pub(crate) fn epoch_offset(secs: i64) -> Option<SystemTime> {
+    if secs < 0 {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))
+    } else {
+        UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))
+    }
+}
+
+
+/// Compute the number of days in a month of the proleptic Gregorian calendar
+///
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+
+/// Compute the number of days since the Unix epoch for a date
+///
+/// The date is given in the proleptic Gregorian calendar. The algorithm is the
+/// one described by Howard Hinnant in "chrono-Compatible Low-Level Date
+/// Algorithms".
+///
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+
+
+/// Compute the date for a number of days since the Unix epoch
+///
+/// This is the inverse of `days_from_civil`.
+///
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamp(string: &str) -> u64 {
+        parse_timestamp(string)
+            .expect("Failed to parse timestamp")
+            .duration_since(UNIX_EPOCH)
+            .expect("Timestamp before epoch")
+            .as_secs()
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(timestamp("1970-01-01"), 0);
+        assert_eq!(timestamp("2000-03-01"), 951868800);
+        assert_eq!(timestamp("2026-12-01"), 1796083200);
+        assert_eq!(timestamp("2026-12-01T12:30:15Z"), 1796083200 + 45015);
+        assert_eq!(timestamp("2026-12-01 12:30"), 1796083200 + 45000);
+        assert_eq!(timestamp("2026-12-01T12:30+02:00"), 1796083200 + 37800);
+        assert_eq!(timestamp("2026-12-01T12:30-0130"), 1796083200 + 50400);
+        assert_eq!(
+            parse_timestamp("1970-01-01T00:00:01.25Z"),
+            Some(UNIX_EPOCH + Duration::from_millis(1250))
+        );
+        assert_eq!(parse_timestamp("1969-12-31"), Some(UNIX_EPOCH - Duration::from_secs(86400)));
+
+        let invalid = [
+            "",
+            "2026",
+            "2026-13-01",
+            "2026-02-29",
+            "26-12-01",
+            "2026-12-01T",
+            "2026-12-01T25:00",
+            "2026-12-01T12",
+            "2026-12-01T12:30.5",
+        ];
+        for invalid in &invalid {
+            assert!(parse_timestamp(invalid).is_none(), "Parsed invalid timestamp {}", invalid);
+        }
+        assert!(parse_timestamp("2024-02-29").is_some());
+    }
+
+    #[test]
+    fn formatting() {
+        let times = [
+            "1970-01-01T00:00:00Z",
+            "1969-12-31T23:59:59.5Z",
+            "2000-02-29T12:00:00Z",
+            "2026-12-01T12:30:15.25Z",
+        ];
+        for time in &times {
+            let parsed = parse_timestamp(time).expect("Failed to parse timestamp");
+            assert_eq!(format_timestamp(parsed), *time);
+        }
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("14d"), Some(Duration::from_secs(14 * 86400)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("2w"), Some(Duration::from_secs(2 * 604800)));
+        assert_eq!(parse_duration("P14D"), Some(Duration::from_secs(14 * 86400)));
+        assert_eq!(parse_duration("PT1H30M"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("P1DT1S"), Some(Duration::from_secs(86401)));
+
+        for invalid in &["", "14", "d", "30m1h", "1d1d", "P", "PT", "P1M", "P1H", "14x"] {
+            assert!(parse_duration(invalid).is_none(), "Parsed invalid duration {}", invalid);
+        }
+    }
+
+    #[test]
+    fn relative() {
+        let now = UNIX_EPOCH + Duration::from_secs(100 * 86400);
+        let at = |s| TimeLiteral::parse(s).expect("Failed to parse time").at(now);
+        assert_eq!(at("now"), Some(now));
+        assert_eq!(at("now-14d"), Some(UNIX_EPOCH + Duration::from_secs(86 * 86400)));
+        assert_eq!(at("now+PT1H"), Some(now + Duration::from_secs(3600)));
+        assert_eq!(at("1970-01-02"), Some(UNIX_EPOCH + Duration::from_secs(86400)));
+        assert!(TimeLiteral::parse("now14d").is_none());
+        assert!(TimeLiteral::parse("now-").is_none());
+    }
+
+    #[test]
+    fn overflow() {
+        let now = SystemTime::now();
+        let at = |s| TimeLiteral::parse(s).expect("Failed to parse time").at(now);
+        assert_eq!(at("now+18446744073709551615s"), None);
+        assert_eq!(at("now-18446744073709551615s"), None);
+        assert!(TimeLiteral::parse("now+18446744073709551616s").is_none());
+
+        // Whether these are representable depends on the platform
+        epoch_offset(i64::MIN);
+        epoch_offset(i64::MAX);
+        assert_eq!(epoch_offset(-1), Some(UNIX_EPOCH - Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,346 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Deserialization of issue states using serde
+//!
+//! This module provides `DeserializeSeed`s for deserializing issue states from
+//! any self-describing format supported by serde. The structure expected is
+//! the same as for the YAML parser: a sequence of nodes, each being either the
+//! name of a state or a map with "name", "conditions", "overrides" and
+//! "extends" entries. Conditions are created using a `ConditionFactory`.
+//!
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use condition::ConditionFactory;
+use resolution::IssueStateSet;
+use state;
+
+
+
+
+/// Seed for deserializing an `IssueStateSet`
+///
+/// The set is expected to be represented as a sequence of states.
+///
+pub struct IssueStatesSeed<'f, C, F>
+    where F: 'f
+{
+    cond_factory: &'f F,
+    phantom: PhantomData<fn() -> C>,
+}
+
+
+impl<'f, C, F> IssueStatesSeed<'f, C, F>
+    where F: ConditionFactory<C> + 'f
+{
+    /// Create a new seed using the given condition factory
+    ///
+    pub fn new(cond_factory: &'f F) -> Self {
+        Self {cond_factory, phantom: PhantomData}
+    }
+}
+
+
+impl<'de, 'f, C, F> DeserializeSeed<'de> for IssueStatesSeed<'f, C, F>
+    where F: ConditionFactory<C> + 'f
+{
+    type Value = IssueStateSet<C>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+
+impl<'de, 'f, C, F> Visitor<'de> for IssueStatesSeed<'f, C, F>
+    where F: ConditionFactory<C> + 'f
+{
+    type Value = IssueStateSet<C>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("sequence of issue states")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where A: SeqAccess<'de>
+    {
+        let mut retval = state::IssueStateVec::default();
+
+        while let Some(state) = seq.next_element_seed(StateSeed {
+            cond_factory: self.cond_factory,
+            existing_states: &retval,
+        })? {
+            retval.push(Arc::new(state));
+        }
+
+        Ok(retval.into())
+    }
+}
+
+
+
+
+/// Seed for deserializing a single `IssueState`
+///
+struct StateSeed<'f, 's, C, F>
+    where C: 's,
+          F: 'f
+{
+    cond_factory: &'f F,
+    existing_states: &'s state::IssueStateVec<C>,
+}
+
+
+impl<'de, 'f, 's, C, F> DeserializeSeed<'de> for StateSeed<'f, 's, C, F>
+    where C: 's,
+          F: ConditionFactory<C> + 'f
+{
+    type Value = state::IssueState<C>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+
+impl<'de, 'f, 's, C, F> Visitor<'de> for StateSeed<'f, 's, C, F>
+    where C: 's,
+          F: ConditionFactory<C> + 'f
+{
+    type Value = state::IssueState<C>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("issue state as either map or string")
+    }
+
+    fn visit_str<E>(self, name: &str) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        Ok(state::IssueState::new(name.to_owned()))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where A: MapAccess<'de>
+    {
+        let mut name = Default::default();
+        let mut conditions = Vec::default();
+        let mut relations = state::StateRelations::default();
+
+        while let Some(key) = map.next_key::<String>()? {
+            let relation = match key.as_str() {
+                "name" => { name = map.next_value()?; continue },
+                "conditions" => {
+                    conditions.extend(map.next_value_seed(ConditionsSeed::new(self.cond_factory))?);
+                    continue
+                },
+                "overrides" => state::StateRelation::Overrides,
+                "extends" => state::StateRelation::Extends,
+                _ => return Err(de::Error::custom(
+                    "Expected either 'name', 'conditions', 'overrides' or 'extends'"
+                )),
+            };
+
+            for name in map.next_value::<Names>()?.0 {
+                let state = self
+                    .existing_states
+                    .iter()
+                    .find(|s| *s.name() == name)
+                    .map(Clone::clone)
+                    .ok_or_else(|| de::Error::custom(format!("Unknown state '{}'", name)))?;
+                relations.insert(state, relation.clone());
+            }
+        }
+
+        let mut retval = state::IssueState::new(name);
+        retval.conditions = conditions;
+        retval.relations = relations;
+        Ok(retval)
+    }
+}
+
+
+
+
+/// Seed for deserializing conditions
+///
+/// The conditions may be given either as a single node or as a sequence of
+/// nodes. Each of the nodes is either a string representing a condition atom
+/// or a map representing a composition of conditions.
+///
+struct ConditionsSeed<'f, C, F>
+    where F: 'f
+{
+    cond_factory: &'f F,
+    /// Whether a sequence of nodes is allowed at this point
+    allow_seq: bool,
+    phantom: PhantomData<fn() -> C>,
+}
+
+
+impl<'f, C, F> ConditionsSeed<'f, C, F>
+    where F: ConditionFactory<C> + 'f
+{
+    fn new(cond_factory: &'f F) -> Self {
+        Self {cond_factory, allow_seq: true, phantom: PhantomData}
+    }
+}
+
+
+impl<'de, 'f, C, F> DeserializeSeed<'de> for ConditionsSeed<'f, C, F>
+    where F: ConditionFactory<C> + 'f
+{
+    type Value = Vec<C>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+
+impl<'de, 'f, C, F> Visitor<'de> for ConditionsSeed<'f, C, F>
+    where F: ConditionFactory<C> + 'f
+{
+    type Value = Vec<C>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.allow_seq {
+            f.write_str("condition, composition or sequence of conditions")
+        } else {
+            f.write_str("condition as either string or map")
+        }
+    }
+
+    fn visit_str<E>(self, atom: &str) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        self.cond_factory
+            .parse_condition(atom)
+            .map(|cond| vec![cond])
+            .map_err(de::Error::custom)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where A: SeqAccess<'de>
+    {
+        if !self.allow_seq {
+            return Err(de::Error::invalid_type(de::Unexpected::Seq, &self));
+        }
+
+        let mut retval = Vec::default();
+        while let Some(conditions) = seq.next_element_seed(ConditionsSeed {
+            cond_factory: self.cond_factory,
+            allow_seq: false,
+            phantom: PhantomData,
+        })? {
+            retval.extend(conditions);
+        }
+        Ok(retval)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where A: MapAccess<'de>
+    {
+        let factory = self.cond_factory;
+        let mut retval = Vec::default();
+
+        while let Some(key) = map.next_key::<String>()? {
+            let mut conditions = map.next_value_seed(ConditionsSeed::new(factory))?;
+            let condition = match key.as_str() {
+                "all" => factory.make_conjunction(conditions),
+                "any" => factory.make_disjunction(conditions),
+                "not" => if conditions.len() == 1 {
+                    factory.make_negation(conditions.remove(0))
+                } else {
+                    factory
+                        .make_conjunction(conditions)
+                        .and_then(|cond| factory.make_negation(cond))
+                },
+                _ => return Err(de::Error::custom("Expected either 'all', 'any' or 'not'")),
+            };
+
+            retval.push(condition.map_err(de::Error::custom)?);
+        }
+
+        Ok(retval)
+    }
+}
+
+
+
+
+/// List of state names
+///
+/// The list may be represented either as a sequence of strings or as a single
+/// string.
+///
+struct Names(Vec<String>);
+
+
+impl<'de> Deserialize<'de> for Names {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_any(NamesVisitor)
+    }
+}
+
+
+struct NamesVisitor;
+
+
+impl<'de> Visitor<'de> for NamesVisitor {
+    type Value = Names;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("string or sequence of strings")
+    }
+
+    fn visit_str<E>(self, name: &str) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        Ok(Names(vec![name.to_owned()]))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where A: SeqAccess<'de>
+    {
+        let mut retval = Vec::default();
+        while let Some(name) = seq.next_element()? {
+            retval.push(name);
+        }
+        Ok(Names(retval))
+    }
+}
@@ -0,0 +1,264 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Export of issue states as Graphviz DOT graphs
+//!
+//! This module provides the `Dot` type, which renders the relations between the
+//! states of an `IssueStateSet` as a directed graph in the DOT language. States
+//! are rendered as nodes, labelled with the state's name and its conditions.
+//! Relations are rendered as edges from the extending or overriding state to
+//! the extended or overridden state, respectively.
+//!
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+use condition::Condition;
+use resolution::IssueStateSet;
+use state::{IssueState, StateRelation};
+
+
+
+
+/// DOT representation of an `IssueStateSet`
+///
+/// The graph is rendered via the `Display` implementation. Conditions are
+/// rendered using their `Display` implementation.
+///
+pub struct Dot<'a, C>
+    where C: Condition + 'a
+{
+    states: &'a IssueStateSet<C>,
+    reduced: bool,
+}
+
+
+impl<'a, C> Dot<'a, C>
+    where C: Condition + 'a
+{
+    /// Create a DOT representation of a set of issue states
+    ///
+    pub fn new(states: &'a IssueStateSet<C>) -> Self {
+        Self {states, reduced: false}
+    }
+
+    /// Only render the transitive reduction of the relations
+    ///
+    /// If set, a relation between two states is not rendered if the related
+    /// state is also reachable via other relations. `Extends` relations are
+    /// only considered implied by other `Extends` relations.
+    ///
+    pub fn transitively_reduced(self, reduced: bool) -> Self {
+        Self {reduced, ..self}
+    }
+
+    /// Compute the set of redundant relations
+    ///
+    /// An `Overrides` relation from one state to another is redundant if the
+    /// latter is reachable via other relations. An `Extends` relation is
+    /// redundant only if the related state is reachable via other `Extends`
+    /// relations, since only those require the related state to be enabled.
+    ///
+    fn redundant(&self) -> BTreeSet<(&'a IssueState<C>, &'a IssueState<C>)> {
+        // Since dependencies are yielded first, we can compute the states
+        // reachable from any given state in one pass. We keep track of states
+        // reachable via any relations and via `Extends` relations only.
+        type Reachable<'s, C> = BTreeMap<&'s IssueState<C>, BTreeSet<&'s IssueState<C>>>;
+        let mut reachable: Reachable<C> = BTreeMap::new();
+        let mut extended: Reachable<C> = BTreeMap::new();
+        let mut retval = BTreeSet::new();
+
+        for state in self.states.iter() {
+            let mut reach = BTreeSet::new();
+            let mut extends = BTreeSet::new();
+            for (dependency, relation) in state.relations.iter() {
+                if let Some(transitive) = reachable.get(dependency.as_ref()) {
+                    reach.extend(transitive.iter().cloned());
+                }
+                if *relation == StateRelation::Extends {
+                    if let Some(transitive) = extended.get(dependency.as_ref()) {
+                        extends.extend(transitive.iter().cloned());
+                    }
+                }
+            }
+
+            for (dependency, relation) in state.relations.iter() {
+                let implied = match *relation {
+                    StateRelation::Extends => extends.contains(dependency.as_ref()),
+                    StateRelation::Overrides => reach.contains(dependency.as_ref()),
+                };
+                if implied {
+                    retval.insert((state.as_ref(), dependency.as_ref()));
+                }
+            }
+
+            reach.extend(state.relations.keys().map(AsRef::as_ref));
+            extends.extend(state
+                .relations
+                .iter()
+                .filter(|r| *r.1 == StateRelation::Extends)
+                .map(|r| r.0.as_ref()));
+            reachable.insert(state.as_ref(), reach);
+            extended.insert(state.as_ref(), extends);
+        }
+
+        retval
+    }
+}
+
+
+impl<'a, C> fmt::Display for Dot<'a, C>
+    where C: Condition + fmt::Display + 'a
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let redundant = if self.reduced { self.redundant() } else { Default::default() };
+
+        writeln!(f, "digraph {{")?;
+
+        for state in self.states.iter() {
+            let mut label = state.name().clone();
+            for condition in state.conditions.iter() {
+                label.push('\n');
+                label.push_str(condition.to_string().as_str());
+            }
+            writeln!(f, "    {} [label={}];", Quoted(state.name()), Quoted(&label))?;
+        }
+
+        for state in self.states.iter() {
+            for (dependency, relation) in state.relations.iter() {
+                if redundant.contains(&(state.as_ref(), dependency.as_ref())) {
+                    continue;
+                }
+
+                let style = match *relation {
+                    StateRelation::Extends => "[style=solid, label=\"extends\"]",
+                    StateRelation::Overrides => "[style=dashed, label=\"overrides\"]",
+                };
+                writeln!(
+                    f,
+                    "    {} -> {} {};",
+                    Quoted(state.name()),
+                    Quoted(dependency.name()),
+                    style
+                )?;
+            }
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+
+/// Helper for rendering quoted DOT strings
+///
+struct Quoted<'s>(&'s str);
+
+
+impl<'s> fmt::Display for Quoted<'s> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use test::{example_states, TestState};
+
+    #[test]
+    fn smoke() {
+        let states = example_states();
+        assert_eq!(Dot::new(&states).to_string(), r#"digraph {
+    "new" [label="new"];
+    "acknowledged" [label="acknowledged\nacked"];
+    "assigned" [label="assigned\nassigned"];
+    "closed" [label="closed\nclosed"];
+    "acknowledged" -> "new" [style=dashed, label="overrides"];
+    "assigned" -> "acknowledged" [style=solid, label="extends"];
+    "closed" -> "assigned" [style=dashed, label="overrides"];
+}
+"#);
+    }
+
+    #[test]
+    fn reduced() {
+        let new : Arc<TestState> = IssueState::new("new".to_string()).into();
+        let assigned : Arc<TestState> = {
+            let mut tmp = IssueState::new("assigned".to_string());
+            tmp.add_overridden(vec![new.clone()]);
+            tmp
+        }.into();
+        let closed : Arc<TestState> = {
+            let mut tmp = IssueState::new("\"closed\"".to_string());
+            tmp.add_overridden(vec![new.clone(), assigned.clone()]);
+            tmp
+        }.into();
+        let states = vec![new, assigned, closed].into();
+
+        let full = Dot::new(&states).to_string();
+        assert!(full.contains(r#""\"closed\"" -> "new""#));
+        assert!(full.contains(r#""\"closed\"" -> "assigned""#));
+
+        let reduced = Dot::new(&states).transitively_reduced(true).to_string();
+        assert!(!reduced.contains(r#""\"closed\"" -> "new""#));
+        assert!(reduced.contains(r#""\"closed\"" -> "assigned""#));
+        assert!(reduced.contains(r#""assigned" -> "new""#));
+    }
+
+    #[test]
+    fn reduced_mixed() {
+        let state = |name: &str, extends: &[&Arc<TestState>], overrides: &[&Arc<TestState>]| {
+            let mut tmp = IssueState::new(name.to_string());
+            tmp.add_extended(extends.iter().map(|s| (*s).clone()));
+            tmp.add_overridden(overrides.iter().map(|s| (*s).clone()));
+            Arc::new(tmp)
+        };
+        let a = state("a", &[], &[]);
+        let b = state("b", &[&a], &[]);
+        let c = state("c", &[&a], &[&b]);
+        let d = state("d", &[&a, &b], &[]);
+        let states = vec![a, b, c, d].into();
+
+        // An extended state reachable via an overridden one is still required
+        let reduced = Dot::new(&states).transitively_reduced(true).to_string();
+        assert!(reduced.contains(r#""c" -> "a" [style=solid, label="extends"]"#));
+        assert!(reduced.contains(r#""c" -> "b" [style=dashed, label="overrides"]"#));
+        assert!(!reduced.contains(r#""d" -> "a""#));
+        assert!(reduced.contains(r#""d" -> "b" [style=solid, label="extends"]"#));
+    }
+}
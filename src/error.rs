@@ -31,6 +31,8 @@ use std::fmt;
 use std::error::Error as EError;
 use std::result::Result as RResult;
 
+use condition::MatchOp;
+
 
 
 
@@ -46,6 +48,46 @@ pub enum ErrorKind {
     ///
     DependencyError,
     ConditionParseError,
+    /// Conditions can not be composed
+    ///
+    /// Not all condition types support composition of conditions, e.g. as
+    /// conjunction, disjunction or negation.
+    ///
+    CompositionUnsupported,
+    /// More than one maximal state is enabled for an issue
+    ///
+    /// The names of the conflicting states are included.
+    ///
+    AmbiguousState(Vec<String>),
+    /// The evaluation of a condition failed
+    ///
+    /// The name of the state and the index of the condition within the state's
+    /// conditions are included. The error reported by the condition is
+    /// available as the error's `source()`.
+    ///
+    ConditionFailed {state: String, condition: usize},
+    /// A pattern in a condition atom is invalid
+    ///
+    /// The pattern is included.
+    ///
+    InvalidPattern(String),
+    /// A match operator is not supported
+    ///
+    /// For example, regular expressions are only supported if the library is
+    /// compiled with the `regex` feature enabled.
+    ///
+    OperatorUnsupported(MatchOp),
+    /// A cyclic dependency was detected among related issues
+    ///
+    /// The identifiers of the issues forming the cycle are included, starting
+    /// and ending with the same issue.
+    ///
+    CyclicIssueDependency(Vec<String>),
+    /// A related issue could not be found
+    ///
+    /// The identifier of the issue is included.
+    ///
+    IssueNotFound(String),
 }
 
 
@@ -54,13 +96,29 @@ pub enum ErrorKind {
 /// Error type for use within the library
 ///
 pub struct Error {
-    kind: ErrorKind
+    kind: ErrorKind,
+    source: Option<BoxError>,
+}
+
+
+impl Error {
+    /// Retrieve the kind of the error
+    ///
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Create an error of a given kind caused by another error
+    ///
+    pub fn with_source(kind: ErrorKind, source: BoxError) -> Self {
+        Self {kind, source: Some(source)}
+    }
 }
 
 
 impl From<ErrorKind> for Error {
     fn from(kind: ErrorKind) -> Self {
-        Self {kind: kind}
+        Self {kind, source: None}
     }
 }
 
@@ -71,6 +129,29 @@ impl fmt::Display for Error {
             ErrorKind::CyclicDependency => f.write_str("dependency cycle detected"),
             ErrorKind::DependencyError => f.write_str("dependency resolution error"),
             ErrorKind::ConditionParseError =>  f.write_str("could not parse condition"),
+            ErrorKind::CompositionUnsupported =>
+                f.write_str("composition of conditions is not supported"),
+            ErrorKind::AmbiguousState(ref states) => write!(
+                f,
+                "ambiguous state, candidates are: {}",
+                states.join(", ")
+            ),
+            ErrorKind::InvalidPattern(ref pattern) => write!(f, "invalid pattern '{}'", pattern),
+            ErrorKind::OperatorUnsupported(ref op) =>
+                write!(f, "match operator '{}' is not supported", op),
+            ErrorKind::CyclicIssueDependency(ref issues) => write!(
+                f,
+                "dependency cycle among issues: {}",
+                issues.join(" -> ")
+            ),
+            ErrorKind::IssueNotFound(ref issue) => write!(f, "issue '{}' not found", issue),
+            ErrorKind::ConditionFailed {ref state, condition} => {
+                write!(f, "evaluation of condition {} of state '{}' failed", condition, state)?;
+                match self.source {
+                    Some(ref source) => write!(f, ": {}", source),
+                    None => Ok(()),
+                }
+            },
         }
     }
 }
@@ -87,6 +168,10 @@ impl EError for Error {
     fn description(&self) -> &str {
         "Resolution failed"
     }
+
+    fn source(&self) -> Option<&(dyn EError + 'static)> {
+        self.source.as_ref().map(|e| e.as_ref() as &(dyn EError + 'static))
+    }
 }
 
 
@@ -96,3 +181,8 @@ impl EError for Error {
 ///
 pub type Result<T> = RResult<T, Error>;
 
+
+/// Convenience type for errors reported by users' implementations
+///
+pub type BoxError = Box<dyn EError + Send + Sync>;
+
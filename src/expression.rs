@@ -0,0 +1,352 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Boolean expressions over conditions
+//!
+//! This module provides the `Expression` type, which allows composing
+//! arbitrary Boolean expressions from a user's condition atoms. Since an
+//! `Expression` is a `Condition` itself, `IssueState`s may directly use
+//! expressions as conditions.
+//!
+
+use std::fmt;
+use std::result::Result as RResult;
+
+use condition::{all_partial, Condition, ConditionFactory, MatchOp};
+use context::Context;
+use error::BoxError;
+
+
+
+
+/// Boolean expression over conditions
+///
+/// An expression is either a single condition atom or a conjunction,
+/// disjunction or negation of other expressions.
+///
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Expression<C> {
+    /// A single condition
+    Atom(C),
+    /// Conjunction of expressions
+    ///
+    /// The conjunction of an empty list is true.
+    And(Vec<Expression<C>>),
+    /// Disjunction of expressions
+    ///
+    /// The disjunction of an empty list is false.
+    Or(Vec<Expression<C>>),
+    /// Negation of an expression
+    Not(Box<Expression<C>>),
+}
+
+
+impl<C> Condition for Expression<C>
+    where C: Condition
+{
+    type Issue = C::Issue;
+
+    fn satisfied_by(&self, issue: &Self::Issue) -> bool {
+        match *self {
+            Expression::Atom(ref c) => c.satisfied_by(issue),
+            Expression::And(ref e) => e.iter().all(|e| e.satisfied_by(issue)),
+            Expression::Or(ref e) => e.iter().any(|e| e.satisfied_by(issue)),
+            Expression::Not(ref e) => !e.satisfied_by(issue),
+        }
+    }
+
+    fn try_satisfied_by(&self, issue: &Self::Issue) -> RResult<bool, BoxError> {
+        self.try_satisfied_in(issue, &Context::default())
+    }
+
+    fn try_satisfied_in(
+        &self,
+        issue: &Self::Issue,
+        context: &Context
+    ) -> RResult<bool, BoxError> {
+        // Like `satisfied_by()`, we short-circuit conjunctions and
+        // disjunctions. Only the sub-expressions actually evaluated may fail.
+        match *self {
+            Expression::Atom(ref c) => c.try_satisfied_in(issue, context),
+            Expression::And(ref e) => {
+                for e in e {
+                    if !e.try_satisfied_in(issue, context)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            },
+            Expression::Or(ref e) => {
+                for e in e {
+                    if e.try_satisfied_in(issue, context)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            },
+            Expression::Not(ref e) => e.try_satisfied_in(issue, context).map(|v| !v),
+        }
+    }
+
+    fn satisfied_by_partial(&self, issue: &Self::Issue) -> Option<bool> {
+        self.satisfied_by_partial_in(issue, &Context::default())
+    }
+
+    fn satisfied_by_partial_in(&self, issue: &Self::Issue, context: &Context) -> Option<bool> {
+        // Unknown results are propagated according to Kleene's three-valued
+        // logic. Disjunctions are computed as negated conjunctions of the
+        // negated sub-expressions.
+        match *self {
+            Expression::Atom(ref c) => c.satisfied_by_partial_in(issue, context),
+            Expression::And(ref e) => {
+                all_partial(e.iter().map(|e| e.satisfied_by_partial_in(issue, context)))
+            },
+            Expression::Or(ref e) => {
+                let negated = e
+                    .iter()
+                    .map(|e| e.satisfied_by_partial_in(issue, context).map(|v| !v));
+                all_partial(negated).map(|v| !v)
+            },
+            Expression::Not(ref e) => e.satisfied_by_partial_in(issue, context).map(|v| !v),
+        }
+    }
+}
+
+
+/// Trait for conditions which may be viewed as an expression over atoms
+///
+/// This trait allows inspecting the structure of composed conditions, e.g. for
+/// analysing issue states statically.
+///
+pub trait ToExpression {
+    type Atom;
+
+    /// Retrieve an expression over references to the condition's atoms
+    ///
+    fn to_expression(&self) -> Expression<&Self::Atom>;
+}
+
+
+impl<C> ToExpression for Expression<C> {
+    type Atom = C;
+
+    fn to_expression(&self) -> Expression<&C> {
+        match *self {
+            Expression::Atom(ref c) => Expression::Atom(c),
+            Expression::And(ref e) => Expression::And(e.iter().map(ToExpression::to_expression).collect()),
+            Expression::Or(ref e) => Expression::Or(e.iter().map(ToExpression::to_expression).collect()),
+            Expression::Not(ref e) => Expression::Not(Box::new(e.to_expression())),
+        }
+    }
+}
+
+
+/// Display an expression
+///
+/// Atoms are displayed as is, compositions are displayed as `all(...)`,
+/// `any(...)` and `not(...)`, respectively.
+///
+impl<C> fmt::Display for Expression<C>
+    where C: fmt::Display
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (name, expressions) = match *self {
+            Expression::Atom(ref c) => return c.fmt(f),
+            Expression::And(ref e) => ("all", e.as_slice()),
+            Expression::Or(ref e) => ("any", e.as_slice()),
+            Expression::Not(ref e) => ("not", ::std::slice::from_ref(e.as_ref())),
+        };
+
+        write!(f, "{}(", name)?;
+        for (i, e) in expressions.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            e.fmt(f)?;
+        }
+        f.write_str(")")
+    }
+}
+
+
+impl<C> From<C> for Expression<C> {
+    fn from(condition: C) -> Self {
+        Expression::Atom(condition)
+    }
+}
+
+
+
+
+/// Factory for expressions
+///
+/// This factory wraps a `ConditionFactory` for the user's condition type,
+/// creating `Expression`s of those conditions. Contrary to most factories, it
+/// supports the composition of conditions.
+///
+pub struct ExpressionFactory<F> {
+    inner: F,
+}
+
+
+impl<F> ExpressionFactory<F> {
+    /// Create an expression factory wrapping a given condition factory
+    ///
+    pub fn new(inner: F) -> Self {
+        Self {inner}
+    }
+}
+
+
+impl<F> Default for ExpressionFactory<F>
+    where F: Default
+{
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+
+impl<C, F> ConditionFactory<Expression<C>> for ExpressionFactory<F>
+    where F: ConditionFactory<C>
+{
+    type Error = F::Error;
+
+    fn make_condition(
+        &self,
+        name: &str,
+        neg: bool,
+        val_op: Option<(MatchOp, &str)>
+    ) -> RResult<Expression<C>, Self::Error> {
+        self.inner.make_condition(name, neg, val_op).map(Expression::Atom)
+    }
+
+    fn make_conjunction(
+        &self,
+        conditions: Vec<Expression<C>>
+    ) -> RResult<Expression<C>, Self::Error> {
+        Ok(Expression::And(conditions))
+    }
+
+    fn make_disjunction(
+        &self,
+        conditions: Vec<Expression<C>>
+    ) -> RResult<Expression<C>, Self::Error> {
+        Ok(Expression::Or(conditions))
+    }
+
+    fn make_negation(
+        &self,
+        condition: Expression<C>
+    ) -> RResult<Expression<C>, Self::Error> {
+        Ok(Expression::Not(Box::new(condition)))
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use test::TestCond;
+
+    #[test]
+    fn smoke() {
+        let mut issue = BTreeMap::new();
+        issue.insert("foo", true);
+        issue.insert("bar", false);
+
+        let atom = |name: &'static str| Expression::Atom(TestCond::from(name));
+
+        assert!(atom("foo").satisfied_by(&issue));
+        assert!(!atom("bar").satisfied_by(&issue));
+
+        assert!(Expression::<TestCond>::And(vec![]).satisfied_by(&issue));
+        assert!(Expression::And(vec![atom("foo")]).satisfied_by(&issue));
+        assert!(!Expression::And(vec![atom("foo"), atom("bar")]).satisfied_by(&issue));
+
+        assert!(!Expression::<TestCond>::Or(vec![]).satisfied_by(&issue));
+        assert!(Expression::Or(vec![atom("foo"), atom("bar")]).satisfied_by(&issue));
+        assert!(!Expression::Or(vec![atom("bar"), atom("baz")]).satisfied_by(&issue));
+
+        assert!(!Expression::Not(Box::new(atom("foo"))).satisfied_by(&issue));
+        assert!(Expression::Not(Box::new(
+            Expression::And(vec![atom("foo"), atom("bar")])
+        )).satisfied_by(&issue));
+    }
+
+    #[test]
+    fn partial_evaluation() {
+        let mut issue = BTreeMap::new();
+        issue.insert("foo", true);
+        issue.insert("bar", false);
+
+        // "baz" is unknown
+        let atom = |name: &'static str| Expression::Atom(TestCond::from(name));
+        let partial = |e: Expression<TestCond>| e.satisfied_by_partial(&issue);
+
+        assert_eq!(partial(atom("baz")), None);
+        assert_eq!(partial(Expression::And(vec![atom("foo"), atom("baz")])), None);
+        assert_eq!(partial(Expression::And(vec![atom("bar"), atom("baz")])), Some(false));
+        assert_eq!(partial(Expression::Or(vec![atom("bar"), atom("baz")])), None);
+        assert_eq!(partial(Expression::Or(vec![atom("baz"), atom("foo")])), Some(true));
+        assert_eq!(partial(Expression::Not(Box::new(atom("baz")))), None);
+        assert_eq!(partial(Expression::Not(Box::new(atom("bar")))), Some(true));
+    }
+
+    #[test]
+    fn display() {
+        let atom = |name: &'static str| Expression::Atom(TestCond::from(name));
+        let expr = Expression::Or(vec![
+            atom("foo"),
+            Expression::Not(Box::new(Expression::And(vec![atom("bar"), atom("baz")]))),
+        ]);
+        assert_eq!(expr.to_string(), "any(foo, not(all(bar, baz)))");
+    }
+
+    #[test]
+    fn resolution() {
+        use resolution::{IssueStateSet, Resolvable};
+        use state::IssueState;
+
+        let atom = |name: &'static str| Expression::Atom(TestCond::from(name));
+
+        let mut state = IssueState::new("assigned".to_string());
+        state.conditions = vec![Expression::Or(vec![atom("assignee"), atom("milestone")])];
+        let states: IssueStateSet<_> = vec![state.into()].into();
+
+        let mut issue = BTreeMap::new();
+        assert!(states.issue_state(&issue).expect("Failed to determine state.").is_none());
+
+        issue.insert("milestone", true);
+        let state = states
+            .issue_state(&issue)
+            .expect("Failed to determine state.")
+            .expect("Wrongly determined no state.");
+        assert_eq!(state.name(), "assigned");
+    }
+}
@@ -61,7 +61,7 @@ impl<L, R, K, U, V> LeftJoin<L, R, K, U, V>
     /// Create a new `LeftJoin`
     ///
     pub fn new(left: L, right: R) -> Self {
-        Self {left: left, right: right, buf: None}
+        Self {left, right, buf: None}
     }
 
     /// Get the next right element for a given "left" key
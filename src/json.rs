@@ -0,0 +1,203 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Parse issue states from a JSON document using serde_json
+//!
+//! The primary function for parsing issue states is `parse_issue_states()`. The
+//! states are expected to appear in an array. Each item within the array
+//! must be either the name of a state or an object containing:
+//! * a "name" entry denoting the name of the state,
+//! * an optional "conditions" entry containing conditions, as an array of
+//!   strings or objects composing conditions via "all", "any" or "not"
+//!   entries,
+//! * an optional "overrides" entry containing an array of state names
+//!   apprearing _prior_ to the current issue state in the toplevel array,
+//!   and
+//! * an optional "extends" entry containing an array of state names
+//!   apprearing _prior_ to the current issue state in the toplevel array.
+//!
+//! Single strings may be used in place of arrays containing only one item.
+//!
+
+use std::result::Result as RResult;
+use serde::de::DeserializeSeed;
+use serde_json::{de, Error};
+
+use condition;
+use de::IssueStatesSeed;
+use resolution::IssueStateSet;
+
+
+
+
+/// Parser specific result type
+///
+/// Errors carry the position at which they occured, which is accessible via
+/// `Error::line()` and `Error::column()`.
+///
+pub type ParseResult<T> = RResult<T, Error>;
+
+
+/// Parse issue states from a JSON document or stream
+///
+/// The function expects the `deserializer` used for parsing the document as
+/// well as a `cond_factory` for creating the user-provided condition type.
+///
+/// # Note:
+///
+/// After the deserializer reached the end of the array, the function will
+/// return. Checking for trailing data is the caller's responsibility, e.g. via
+/// `Deserializer::end()`.
+///
+pub fn parse_issue_states<'de, R, C, F>(
+    deserializer: &mut de::Deserializer<R>,
+    cond_factory: F
+) -> ParseResult<IssueStateSet<C>>
+    where R: de::Read<'de>,
+          F: condition::ConditionFactory<C>,
+{
+    IssueStatesSeed::new(&cond_factory).deserialize(deserializer)
+}
+
+
+/// Parse issue states from a string containing a JSON document
+///
+/// This function parses the issue states using `parse_issue_states()`. The
+/// document must not contain anything but the array of issue states.
+///
+pub fn from_str<C, F>(s: &str, cond_factory: F) -> ParseResult<IssueStateSet<C>>
+    where F: condition::ConditionFactory<C>,
+{
+    let mut deserializer = de::Deserializer::from_str(s);
+    let retval = parse_issue_states(&mut deserializer, cond_factory)?;
+    deserializer.end()?;
+    Ok(retval)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expression::{Expression, ExpressionFactory};
+    use state;
+    use test::{TestCond, TestCondFactory};
+
+    // Convenience function for encapsulating boilerplate for each test
+    //
+    fn parse(s: &str) -> IssueStateSet<TestCond> {
+        from_str(s, TestCondFactory::default()).expect("Failed to parse document")
+    }
+
+    #[test]
+    fn empty_array() {
+        let result = parse("[]");
+        assert_eq!(result.iter().count(), 0);
+    }
+
+    #[test]
+    fn single_state() {
+        let result = parse(r#"["foobar"]"#);
+        let mut iter = result.iter();
+
+        let state = iter
+            .next()
+            .expect("Parse result does not contain expected state.");
+        assert_eq!(state.name(), "foobar");
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn multiple_with_conditions() {
+        let result = parse(r#"[
+            "new",
+            {"name": "acknowledged", "conditions": "acked", "overrides": "new"},
+            {"name": "assigned", "conditions": ["assigned"], "extends": "acknowledged"},
+            {"name": "closed", "conditions": ["closed"], "overrides": ["new", "acknowledged", "assigned"]}
+        ]"#);
+
+        let mut iter = result.iter();
+
+        let state1 = iter
+            .next()
+            .expect("Parse result does not contain expected state.");
+        assert_eq!(state1.name(), "new");
+
+        let state2 = iter
+            .next()
+            .expect("Parse result does not contain expected state.");
+        assert_eq!(state2.name(), "acknowledged");
+        assert_eq!(state2.conditions, vec!["acked".into()]);
+        assert_eq!(state2.relations.get(state1), Some(&state::StateRelation::Overrides));
+
+        let state3 = iter
+            .next()
+            .expect("Parse result does not contain expected state.");
+        assert_eq!(state3.name(), "assigned");
+        assert_eq!(state3.conditions, vec!["assigned".into()]);
+        assert_eq!(state3.relations.get(state2), Some(&state::StateRelation::Extends));
+
+        let state4 = iter
+            .next()
+            .expect("Parse result does not contain expected state.");
+        assert_eq!(state4.name(), "closed");
+        assert_eq!(state4.conditions, vec!["closed".into()]);
+        assert_eq!(state4.relations.get(state1), Some(&state::StateRelation::Overrides));
+        assert_eq!(state4.relations.get(state2), Some(&state::StateRelation::Overrides));
+        assert_eq!(state4.relations.get(state3), Some(&state::StateRelation::Overrides));
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn composed_conditions() {
+        let result: IssueStateSet<Expression<TestCond>> = from_str(
+            r#"[{"name": "foobar", "conditions": ["foo", {"any": ["bar", "baz"]}, {"not": "bar"}]}]"#,
+            ExpressionFactory::<TestCondFactory>::default()
+        ).expect("Failed to parse document");
+
+        let state = result
+            .iter()
+            .next()
+            .expect("Parse result does not contain expected state.");
+
+        let atom = |name: &'static str| Expression::Atom(TestCond::from(name));
+        assert_eq!(state.conditions, vec![
+            atom("foo"),
+            Expression::Or(vec![atom("bar"), atom("baz")]),
+            Expression::Not(Box::new(atom("bar"))),
+        ]);
+    }
+
+    #[test]
+    fn error_position() {
+        let err = from_str("[\n  \"new\",\n  {\"name\": \"closed\", \"overrides\": \"open\"}\n]", TestCondFactory::default())
+            .err()
+            .expect("Unknown state not detected");
+        assert_eq!(err.line(), 3);
+    }
+}
@@ -34,27 +34,102 @@
 //! the user's issue-type (or, for example, a type representing an issue's
 //! metadata) to the `IssueState`s provided by this library.
 //!
+//! For issue metadata accessible through a string-keyed lookup, the library
+//! provides the ready-made `MetadataCondition` as an alternative. Custom
+//! condition types may use the `value` module for matching typed values
+//! against the literals in condition atoms. Matching against regular
+//! expressions requires the `regex` feature. Parsers for semantic versions,
+//! dates, timestamps and durations are provided by the `version` and
+//! `datetime` modules.
+//!
 //! Given some issue-states, an `IssueStateSet` may be constructed. This type
 //! allows resolving a given issue's state, honouring relations between the
 //! states contained in the set.
 //!
+//! A state's conditions are interpreted as a conjunction. Arbitrary Boolean
+//! expressions over a user's conditions may be expressed using the
+//! `Expression` type.
+//!
+//! For resolving the states of large numbers of issues, an `IssueStateSet`
+//! may be compiled into a `compiled::CompiledStateSet`. If this library is
+//! compiled with support for `rayon` enabled, both types also allow resolving
+//! the states of many issues in parallel.
+//!
+//! If an explanation is required why a specific state was selected for an
+//! issue, the resolution may be traced via `IssueStateSet::trace()`.
+//!
+//! If not all of an issue's metadata is available, the states which may still
+//! be selected for the issue can be determined via
+//! `IssueStateSet::possible_states()`.
+//!
+//! Conditions depending on the states of other issues, e.g. an issue's
+//! blockers, may implement `related::RelatedCondition`. The states of issues
+//! provided by a `related::IssueProvider` may then be resolved using a
+//! `related::RelatedResolver`.
+//!
+//! Conditions which can only be evaluated asynchronously may implement
+//! `asynchronous::AsyncCondition` instead of `Condition`. The state of an issue
+//! may then be resolved using `IssueStateSet::issue_state_async()`.
+//!
+//! An `IssueStateSet` may be checked for inconsistencies, e.g. states which
+//! can never be selected, via `IssueStateSet::validate()`. Whether each state
+//! can be selected for any issue at all may be determined, together with an
+//! example of the issue's metadata, via `IssueStateSet::reachability()`.
+//!
+//! The relations between the states in an `IssueStateSet` may be visualized
+//! by exporting them as a Graphviz DOT graph using the `dot` module.
+//!
 //! `IssueState`s, and an `IssueStateSet`, may be constructed by the library's
 //! user manually. However, this library also provides means for parsing an
-//! `IssueStateSet` directly from a byte-stream. Currently, only the YAML format
-//! is supported (if this library is compiled with support for `yaml-rust`
-//! enabled).
+//! `IssueStateSet` directly from a byte-stream. Currently, the YAML format
+//! is supported if this library is compiled with support for `yaml-rust`
+//! enabled. The JSON and TOML formats are supported if the `json` and `toml`
+//! features are enabled, respectively.
 //!
 
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "regex")]
+extern crate regex;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde_json")]
+extern crate serde_json;
+#[cfg(feature = "toml")]
+extern crate toml as toml_crate;
 #[cfg(feature = "yaml-rust")]
 extern crate yaml_rust;
 
+pub mod asynchronous;
+pub mod compiled;
 pub mod condition;
+pub mod context;
+pub mod datetime;
+pub mod dot;
 pub mod error;
+pub mod expression;
+pub mod metadata;
+pub mod partial;
+pub mod reachability;
+pub mod related;
 pub mod resolution;
 pub mod state;
+pub mod trace;
+pub mod validation;
+pub mod value;
+pub mod version;
 
 mod iter;
 
+#[cfg(any(feature = "json", feature = "toml"))]
+mod de;
+
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "toml")]
+pub mod toml;
 #[cfg(feature = "yaml-rust")]
 pub mod yaml;
 
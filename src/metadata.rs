@@ -0,0 +1,536 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Generic conditions on issue metadata
+//!
+//! This module provides a ready-made `Condition` implementation, the
+//! `MetadataCondition`, for issues or issue metadata implementing the
+//! `Metadata` trait. The latter allows looking up pieces of metadata by their
+//! identifier, yielding typed `Value`s. The trait is implemented for maps with
+//! string keys.
+//!
+//! The literal in a condition atom is matched against the `Value` as described
+//! in the `value` module. Literals denoting points in time relative to the
+//! current time, e.g. `now-30d`, are interpreted relative to the time provided
+//! by the `Context` in which the condition is evaluated.
+//!
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::result::Result as RResult;
+use std::time::SystemTime;
+
+use condition::{write_atom, Condition, ConditionAtom, ConditionFactory, MatchOp};
+use context::Context;
+use error::{BoxError, Error, Result};
+use expression::{Expression, ToExpression};
+use value::Literal;
+
+pub use value::{ToValue, Value};
+
+
+
+
+/// Trait for issue metadata
+///
+/// Implementers of this trait allow the lookup of individual pieces of
+/// metadata by their metadata identifier.
+///
+pub trait Metadata {
+    /// Retrieve the piece of metadata with the given identifier
+    ///
+    /// If the piece of metadata is not present, e.g. null, this function is
+    /// expected to yield `None`.
+    ///
+    fn get(&self, identifier: &str) -> Option<Value<'_>>;
+
+    /// Check whether the piece of metadata with the given identifier is known
+    ///
+    /// Metadata which is loaded lazily may not be available at the time a
+    /// condition is evaluated. In this case, this function is expected to
+    /// yield `false`, and `get()` to yield `None`. Conditions on unknown
+    /// metadata will be considered neither satisfied nor unsatisfied by
+    /// `Condition::satisfied_by_partial()`.
+    ///
+    /// The default implementation considers all metadata known.
+    ///
+    fn known(&self, _identifier: &str) -> bool {
+        true
+    }
+}
+
+
+impl<K, V> Metadata for BTreeMap<K, V>
+    where K: Borrow<str> + Ord,
+          V: ToValue
+{
+    fn get(&self, identifier: &str) -> Option<Value<'_>> {
+        BTreeMap::get(self, identifier).map(ToValue::to_value)
+    }
+}
+
+
+impl<K, V> Metadata for HashMap<K, V>
+    where K: Borrow<str> + Hash + Eq,
+          V: ToValue
+{
+    fn get(&self, identifier: &str) -> Option<Value<'_>> {
+        HashMap::get(self, identifier).map(ToValue::to_value)
+    }
+}
+
+
+
+
+/// Condition on `Metadata`
+///
+/// This condition represents a single condition atom on a piece of metadata,
+/// which is retrieved through the `Metadata` trait.
+///
+pub struct MetadataCondition<M>
+    where M: Metadata
+{
+    name: String,
+    negated: bool,
+    op_val: Option<(MatchOp, Literal)>,
+    phantom: PhantomData<fn(&M)>,
+}
+
+
+impl<M> MetadataCondition<M>
+    where M: Metadata
+{
+    /// Create a new condition from the bits of a condition atom
+    ///
+    /// If the value is an invalid pattern, the condition will never be
+    /// satisfied. Use `try_new()` for detecting invalid patterns.
+    ///
+    pub fn new(name: &str, negated: bool, op_val: Option<(MatchOp, &str)>) -> Self {
+        Self {
+            name: name.to_owned(),
+            negated,
+            op_val: op_val.map(|(op, val)| {
+                let literal = Literal::new(val, &op).unwrap_or_else(|_| val.into());
+                (op, literal)
+            }),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Create a new condition from the bits of a condition atom, checking the value
+    ///
+    /// Patterns are precompiled. If the value is not a valid pattern, or if
+    /// the match operator is not supported, an error is returned.
+    ///
+    pub fn try_new(name: &str, negated: bool, op_val: Option<(MatchOp, &str)>) -> Result<Self> {
+        let op_val = op_val
+            .map(|(op, val)| Literal::new(val, &op).map(|literal| (op, literal)))
+            .transpose()?;
+        Ok(Self {name: name.to_owned(), negated, op_val, phantom: PhantomData})
+    }
+
+    /// Check whether the condition is satisfied, given the current time
+    ///
+    fn satisfied_at(&self, issue: &M, now: SystemTime) -> bool {
+        let value = issue.get(self.name.as_str());
+        let matches = match self.op_val {
+            Some((ref op, ref literal)) => value.is_some_and(|v| v.matches_at(op, literal, now)),
+            None => value.is_some(),
+        };
+        matches != self.negated
+    }
+}
+
+
+impl<M> Condition for MetadataCondition<M>
+    where M: Metadata
+{
+    type Issue = M;
+
+    fn satisfied_by(&self, issue: &Self::Issue) -> bool {
+        self.satisfied_at(issue, SystemTime::now())
+    }
+
+    fn try_satisfied_in(
+        &self,
+        issue: &Self::Issue,
+        context: &Context
+    ) -> RResult<bool, BoxError> {
+        Ok(self.satisfied_at(issue, context.now()))
+    }
+
+    fn satisfied_by_partial(&self, issue: &Self::Issue) -> Option<bool> {
+        self.satisfied_by_partial_in(issue, &Context::default())
+    }
+
+    fn satisfied_by_partial_in(&self, issue: &Self::Issue, context: &Context) -> Option<bool> {
+        if issue.known(self.name.as_str()) {
+            Some(self.satisfied_at(issue, context.now()))
+        } else {
+            None
+        }
+    }
+}
+
+
+impl<M> ConditionAtom for MetadataCondition<M>
+    where M: Metadata
+{
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn negated(&self) -> bool {
+        self.negated
+    }
+
+    fn op_val(&self) -> Option<(&MatchOp, &str)> {
+        self.op_val.as_ref().map(|(op, literal)| (op, literal.raw()))
+    }
+}
+
+
+impl<M> ToExpression for MetadataCondition<M>
+    where M: Metadata
+{
+    type Atom = Self;
+
+    fn to_expression(&self) -> Expression<&Self> {
+        Expression::Atom(self)
+    }
+}
+
+
+impl<M> fmt::Display for MetadataCondition<M>
+    where M: Metadata
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_atom(f, self.name.as_str(), self.negated, self.op_val())
+    }
+}
+
+
+impl<M> fmt::Debug for MetadataCondition<M>
+    where M: Metadata
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MetadataCondition({})", self)
+    }
+}
+
+
+impl<M> PartialEq for MetadataCondition<M>
+    where M: Metadata
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.negated == other.negated && self.op_val == other.op_val
+    }
+}
+
+
+impl<M> Eq for MetadataCondition<M>
+    where M: Metadata
+{}
+
+
+impl<M> Hash for MetadataCondition<M>
+    where M: Metadata
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.negated.hash(state);
+        self.op_val.hash(state);
+    }
+}
+
+
+impl<M> Clone for MetadataCondition<M>
+    where M: Metadata
+{
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            negated: self.negated,
+            op_val: self.op_val.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+
+
+
+/// Factory for `MetadataCondition`s
+///
+pub struct MetadataConditionFactory<M>
+    where M: Metadata
+{
+    phantom: PhantomData<fn(&M)>,
+}
+
+
+impl<M> Default for MetadataConditionFactory<M>
+    where M: Metadata
+{
+    fn default() -> Self {
+        Self {phantom: PhantomData}
+    }
+}
+
+
+impl<M> ConditionFactory<MetadataCondition<M>> for MetadataConditionFactory<M>
+    where M: Metadata
+{
+    type Error = Error;
+
+    fn make_condition(
+        &self,
+        name: &str,
+        neg: bool,
+        val_op: Option<(MatchOp, &str)>
+    ) -> RResult<MetadataCondition<M>, Self::Error> {
+        MetadataCondition::try_new(name, neg, val_op)
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    type TestMetadata = BTreeMap<&'static str, Value<'static>>;
+
+    fn satisfied(atom: &str, issue: &TestMetadata) -> bool {
+        MetadataConditionFactory::default()
+            .parse_condition(atom)
+            .expect("Failed to parse condition")
+            .satisfied_by(issue)
+    }
+
+    #[test]
+    fn strings() {
+        let mut issue = TestMetadata::new();
+        issue.insert("title", Value::String("Crash on start"));
+
+        assert!(satisfied("title", &issue));
+        assert!(!satisfied("!title", &issue));
+        assert!(!satisfied("assignee", &issue));
+        assert!(satisfied("!assignee", &issue));
+
+        assert!(satisfied("title=Crash on start", &issue));
+        assert!(!satisfied("title!=Crash on start", &issue));
+        assert!(satisfied("title~on", &issue));
+        assert!(!satisfied("title!~on", &issue));
+        assert!(satisfied("title<Dump", &issue));
+        assert!(satisfied("title>Bug", &issue));
+        assert!(satisfied("title<=Crash on start", &issue));
+        assert!(satisfied("title>=Crash", &issue));
+        assert!(!satisfied("title>=Dump", &issue));
+    }
+
+    #[test]
+    fn integers() {
+        let mut issue = TestMetadata::new();
+        issue.insert("votes", Value::Integer(10));
+
+        assert!(satisfied("votes=10", &issue));
+        assert!(satisfied("votes~10", &issue));
+        assert!(satisfied("votes>9", &issue));
+        assert!(satisfied("votes<11", &issue));
+        assert!(satisfied("votes>=10", &issue));
+        assert!(satisfied("votes<=10", &issue));
+        assert!(!satisfied("votes<10", &issue));
+        assert!(satisfied("votes!<10", &issue));
+        assert!(!satisfied("votes=foo", &issue));
+        assert!(satisfied("votes!=foo", &issue));
+    }
+
+    #[test]
+    fn bools() {
+        let mut issue = TestMetadata::new();
+        issue.insert("confirmed", Value::Bool(true));
+
+        assert!(satisfied("confirmed=true", &issue));
+        assert!(satisfied("confirmed~true", &issue));
+        assert!(satisfied("confirmed!=false", &issue));
+        assert!(!satisfied("confirmed<true", &issue));
+    }
+
+    #[test]
+    fn lists() {
+        let mut issue = TestMetadata::new();
+        issue.insert("labels", Value::List(vec![Value::String("bug"), Value::String("ui")]));
+
+        assert!(satisfied("labels~bug", &issue));
+        assert!(!satisfied("labels~feature", &issue));
+        assert!(satisfied("labels!~feature", &issue));
+        assert!(!satisfied("labels=bug", &issue));
+    }
+
+    #[test]
+    fn timestamps() {
+        let mut issue = TestMetadata::new();
+        issue.insert("created", Value::Timestamp(UNIX_EPOCH + Duration::from_secs(1000)));
+
+        assert!(satisfied("created=1000", &issue));
+        assert!(satisfied("created>999", &issue));
+        assert!(satisfied("created<1001", &issue));
+        assert!(!satisfied("created>1000", &issue));
+    }
+
+    #[test]
+    fn relative_timestamps() {
+        let day = Duration::from_secs(86400);
+        let context = Context::new(UNIX_EPOCH + day * 100);
+
+        let mut issue = TestMetadata::new();
+        issue.insert("updated", Value::Timestamp(UNIX_EPOCH + day * 60));
+
+        let satisfied_in = |atom, context: &Context| MetadataConditionFactory::default()
+            .parse_condition(atom)
+            .expect("Failed to parse condition")
+            .try_satisfied_in(&issue, context)
+            .expect("Failed to evaluate condition");
+        assert!(satisfied_in("updated<now-30d", &context));
+        assert!(!satisfied_in("updated<now-60d", &context));
+        assert!(satisfied_in("updated>=now-P40D", &context));
+        assert!(!satisfied_in("updated>now-40d", &context));
+        assert!(satisfied_in("updated<now", &context));
+        assert!(!satisfied_in("updated<now-30d", &Context::new(UNIX_EPOCH + day * 80)));
+    }
+
+    #[test]
+    fn stale_state() {
+        use compiled::CompiledStateSet;
+        use resolution::{IssueStateSet, Resolvable};
+        use state::IssueState;
+
+        let day = Duration::from_secs(86400);
+        let factory : MetadataConditionFactory<TestMetadata> = Default::default();
+
+        let mut state = IssueState::new("stale".to_string());
+        let condition = factory.parse_condition("updated<now-30d").expect("Failed to parse condition");
+        state.conditions = vec![condition];
+        let states: IssueStateSet<_> = vec![state.into()].into();
+
+        let mut issue = TestMetadata::new();
+        issue.insert("updated", Value::Timestamp(UNIX_EPOCH + day * 10));
+
+        let state_at = |days| states
+            .issue_state_in(&issue, &Context::new(UNIX_EPOCH + day * days))
+            .expect("Failed to determine state")
+            .map(|state| state.name().clone());
+        assert_eq!(state_at(30), None);
+        assert_eq!(state_at(41), Some("stale".to_string()));
+
+        // All the means of resolution honor the context
+        let compiled = CompiledStateSet::new(&states).expect("Failed to compile states.");
+        for &(days, stale) in &[(30, false), (41, true)] {
+            let context = Context::new(UNIX_EPOCH + day * days);
+            let enabled = states.enabled_states_in(&issue, &context).expect("Failed to determine states");
+            assert_eq!(enabled.len(), stale as usize);
+            let maximal = states
+                .maximal_enabled_states_in(&issue, &context)
+                .expect("Failed to determine states");
+            assert_eq!(maximal.len(), stale as usize);
+            let strict = states.issue_state_strict_in(&issue, &context).expect("Failed to determine state");
+            assert_eq!(strict.is_some(), stale);
+            let lazy = states.issue_state_lazy_in(&issue, &context).expect("Failed to determine state");
+            assert_eq!(lazy.is_some(), stale);
+            let trace = states.trace_in(&issue, &context).expect("Failed to trace resolution");
+            assert_eq!(trace.selected().is_some(), stale);
+            let batch = compiled
+                .issue_states_in(vec![&issue], &context)
+                .map(|s| s.expect("Failed to determine state").is_some())
+                .collect::<Vec<_>>();
+            assert_eq!(batch, vec![stale]);
+            let possible = states
+                .possible_states_in(&issue, &context)
+                .expect("Failed to determine states");
+            assert_eq!(possible.definite().map(|s| s.is_some()), Some(stale));
+        }
+    }
+
+    #[test]
+    fn partial_relative_timestamps() {
+        let day = Duration::from_secs(86400);
+        let mut issue = TestMetadata::new();
+        issue.insert("updated", Value::Timestamp(UNIX_EPOCH + day * 60));
+
+        let condition : MetadataCondition<TestMetadata> = MetadataConditionFactory::default()
+            .parse_condition("updated<now-30d")
+            .expect("Failed to parse condition");
+        let partial_at = |days| condition
+            .satisfied_by_partial_in(&issue, &Context::new(UNIX_EPOCH + day * days));
+        assert_eq!(partial_at(100), Some(true));
+        assert_eq!(partial_at(80), Some(false));
+        assert_eq!(condition.satisfied_by_partial(&issue), Some(true));
+    }
+
+    #[test]
+    fn display() {
+        let factory : MetadataConditionFactory<TestMetadata> = Default::default();
+        for atom in &["foo", "!foo", "foo=bar", "foo!<=3"] {
+            let cond = factory.parse_condition(atom).expect("Failed to parse condition");
+            assert_eq!(cond.to_string(), *atom);
+        }
+    }
+
+    #[test]
+    fn unknown() {
+        // Metadata of which only "title" has been loaded so far
+        struct Partial(TestMetadata);
+
+        impl Metadata for Partial {
+            fn get(&self, identifier: &str) -> Option<Value<'_>> {
+                Metadata::get(&self.0, identifier)
+            }
+
+            fn known(&self, identifier: &str) -> bool {
+                identifier == "title"
+            }
+        }
+
+        let mut issue = TestMetadata::new();
+        issue.insert("title", Value::String("Crash on start"));
+        let issue = Partial(issue);
+
+        let factory : MetadataConditionFactory<Partial> = Default::default();
+        let partial = |atom| factory
+            .parse_condition(atom)
+            .expect("Failed to parse condition")
+            .satisfied_by_partial(&issue);
+        assert_eq!(partial("title~Crash"), Some(true));
+        assert_eq!(partial("!title"), Some(false));
+        assert_eq!(partial("assignee"), None);
+        assert_eq!(partial("!assignee"), None);
+    }
+}
@@ -0,0 +1,266 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Parallel resolution of issue states
+//!
+//! This module provides functions for resolving the states of collections of
+//! issues across multiple threads, using rayon. The states are returned in the
+//! order of the issues.
+//!
+
+use std::borrow::Borrow;
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use compiled::CompiledStateSet;
+use condition::Condition;
+use context::Context;
+use error::*;
+use resolution::{IssueStateSet, Resolvable};
+use state::IssueState;
+
+
+
+
+impl<C> IssueStateSet<C>
+    where C: Condition + Send + Sync,
+          C::Issue: Sync
+{
+    /// Resolve the states of multiple issues in parallel
+    ///
+    /// This function resolves the state of every issue yielded by `issues`
+    /// using `Resolvable::issue_state()`. The states are returned in the order
+    /// of the issues. Slices and `Vec`s of issues may be passed directly, as
+    /// may any indexed parallel iterator. For sequential iterators, use
+    /// `par_issue_states_iter()`.
+    ///
+    /// If the resolution fails for any issue, an error is returned.
+    ///
+    pub fn par_issue_states<I>(&self, issues: I) -> Result<Vec<Option<Arc<IssueState<C>>>>>
+        where I: IntoParallelIterator,
+              I::Iter: IndexedParallelIterator,
+              I::Item: Borrow<C::Issue>
+    {
+        self.par_issue_states_in(issues, &Context::default())
+    }
+
+    /// Resolve the states of multiple issues in parallel in a given context
+    ///
+    /// Like `par_issue_states()`, but the conditions are evaluated in the
+    /// context provided.
+    ///
+    pub fn par_issue_states_in<I>(
+        &self,
+        issues: I,
+        context: &Context
+    ) -> Result<Vec<Option<Arc<IssueState<C>>>>>
+        where I: IntoParallelIterator,
+              I::Iter: IndexedParallelIterator,
+              I::Item: Borrow<C::Issue>
+    {
+        issues
+            .into_par_iter()
+            .map(|issue| self.issue_state_in(issue.borrow(), context))
+            .collect()
+    }
+
+    /// Resolve the states of issues yielded by a sequential iterator in parallel
+    ///
+    /// Contrary to `par_issue_states()`, this function accepts any iterator
+    /// over issues. The issues are collected before their states are resolved
+    /// in parallel. The states are returned in the order of the issues.
+    ///
+    pub fn par_issue_states_iter<I>(&self, issues: I) -> Result<Vec<Option<Arc<IssueState<C>>>>>
+        where I: IntoIterator,
+              I::Item: Borrow<C::Issue> + Send
+    {
+        self.par_issue_states_iter_in(issues, &Context::default())
+    }
+
+    /// Resolve the states of issues yielded by an iterator in parallel in a given context
+    ///
+    /// Like `par_issue_states_iter()`, but the conditions are evaluated in the
+    /// context provided.
+    ///
+    pub fn par_issue_states_iter_in<I>(
+        &self,
+        issues: I,
+        context: &Context
+    ) -> Result<Vec<Option<Arc<IssueState<C>>>>>
+        where I: IntoIterator,
+              I::Item: Borrow<C::Issue> + Send
+    {
+        self.par_issue_states_in(issues.into_iter().collect::<Vec<_>>(), context)
+    }
+}
+
+
+impl<C> CompiledStateSet<C>
+    where C: Condition + Send + Sync,
+          C::Issue: Sync
+{
+    /// Resolve the states of multiple issues in parallel
+    ///
+    /// This function resolves the state of every issue yielded by `issues`,
+    /// using one scratch buffer per worker. The states are returned in the
+    /// order of the issues. The conditions are evaluated in a default `Context`
+    /// created once for all the issues. Like for
+    /// `IssueStateSet::par_issue_states()`, `issues` needs to be an indexed
+    /// parallel iterator or convertible to one. For sequential iterators, use
+    /// `par_issue_states_iter()`.
+    ///
+    /// If the resolution fails for any issue, an error is returned.
+    ///
+    pub fn par_issue_states<I>(&self, issues: I) -> Result<Vec<Option<&Arc<IssueState<C>>>>>
+        where I: IntoParallelIterator,
+              I::Iter: IndexedParallelIterator,
+              I::Item: Borrow<C::Issue>
+    {
+        self.par_issue_states_in(issues, &Context::default())
+    }
+
+    /// Resolve the states of multiple issues in parallel in a given context
+    ///
+    /// Like `par_issue_states()`, but the conditions are evaluated in the
+    /// context provided.
+    ///
+    pub fn par_issue_states_in<I>(
+        &self,
+        issues: I,
+        context: &Context
+    ) -> Result<Vec<Option<&Arc<IssueState<C>>>>>
+        where I: IntoParallelIterator,
+              I::Iter: IndexedParallelIterator,
+              I::Item: Borrow<C::Issue>
+    {
+        issues
+            .into_par_iter()
+            .map_init(
+                || self.scratch(),
+                |scratch, issue| self.issue_state_with(issue.borrow(), context, scratch)
+            )
+            .collect()
+    }
+
+    /// Resolve the states of issues yielded by a sequential iterator in parallel
+    ///
+    /// Contrary to `par_issue_states()`, this function accepts any iterator
+    /// over issues. The issues are collected before their states are resolved
+    /// in parallel. The states are returned in the order of the issues.
+    ///
+    pub fn par_issue_states_iter<I>(&self, issues: I) -> Result<Vec<Option<&Arc<IssueState<C>>>>>
+        where I: IntoIterator,
+              I::Item: Borrow<C::Issue> + Send
+    {
+        self.par_issue_states_iter_in(issues, &Context::default())
+    }
+
+    /// Resolve the states of issues yielded by an iterator in parallel in a given context
+    ///
+    /// Like `par_issue_states_iter()`, but the conditions are evaluated in the
+    /// context provided.
+    ///
+    pub fn par_issue_states_iter_in<I>(
+        &self,
+        issues: I,
+        context: &Context
+    ) -> Result<Vec<Option<&Arc<IssueState<C>>>>>
+        where I: IntoIterator,
+              I::Item: Borrow<C::Issue> + Send
+    {
+        self.par_issue_states_in(issues.into_iter().collect::<Vec<_>>(), context)
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use test::example_states;
+
+    fn issues() -> Vec<BTreeMap<&'static str, bool>> {
+        (0..1000)
+            .map(|i| {
+                let mut issue = BTreeMap::new();
+                issue.insert("acked", i % 2 == 0);
+                issue.insert("assigned", i % 3 == 0);
+                issue.insert("closed", i % 5 == 0);
+                issue
+            })
+            .collect()
+    }
+
+    #[test]
+    fn order() {
+        let states = example_states();
+        let issues = issues();
+
+        let expected: Vec<_> = issues
+            .iter()
+            .map(|issue| states.issue_state(issue).expect("Failed to determine state."))
+            .collect();
+
+        let result = states
+            .par_issue_states(issues.as_slice())
+            .expect("Failed to determine states.");
+        assert!(result == expected);
+
+        let compiled = CompiledStateSet::new(&states).expect("Failed to compile states.");
+        let result: Vec<_> = compiled
+            .par_issue_states(&issues)
+            .expect("Failed to determine states.")
+            .into_iter()
+            .map(|s| s.cloned())
+            .collect();
+        assert!(result == expected);
+    }
+
+    #[test]
+    fn sequential_iterators() {
+        let states = example_states();
+        let issues = issues();
+
+        let expected = states
+            .par_issue_states(issues.as_slice())
+            .expect("Failed to determine states.");
+
+        let filtered = issues.iter().filter(|_| true);
+        let result = states.par_issue_states_iter(filtered).expect("Failed to determine states.");
+        assert!(result == expected);
+
+        let compiled = CompiledStateSet::new(&states).expect("Failed to compile states.");
+        let result: Vec<_> = compiled
+            .par_issue_states_iter(issues.clone())
+            .expect("Failed to determine states.")
+            .into_iter()
+            .map(|s| s.cloned())
+            .collect();
+        assert!(result == expected);
+    }
+}
@@ -0,0 +1,239 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Resolution of states for partially known issues
+//!
+//! If not all of an issue's metadata is available, it may not be possible to
+//! decide which state is selected for the issue. This module provides the means
+//! for determining the states which may still be selected, given conditions
+//! which evaluate to "unknown" via `Condition::satisfied_by_partial()`.
+//!
+//! Unknown values are propagated according to Kleene's three-valued logic.
+//! Different conditions are treated as independent of each other. Hence, the
+//! states determined may include states which could not actually be selected
+//! for any completion of the issue's metadata.
+//!
+
+use std::collections::BTreeMap;
+use std::slice;
+use std::sync::Arc;
+
+use condition::{all_partial, Condition};
+use context::Context;
+use error::*;
+use resolution::IssueStateSet;
+use state::{IssueState, IssueStateVec, StateRelation};
+
+
+
+
+/// States which may be selected for a partially known issue
+///
+pub struct PossibleStates<C> {
+    /// The states which may be selected, in the order of the set
+    states: IssueStateVec<C>,
+    /// Whether it is possible that no state is selected
+    none: bool,
+}
+
+
+impl<C> PossibleStates<C> {
+    /// Get an iterator over the states which may be selected
+    ///
+    /// The states are yielded in the order in which they appear in the set,
+    /// e.g. a state is yielded only after all its dependencies.
+    ///
+    pub fn iter(&self) -> slice::Iter<'_, Arc<IssueState<C>>> {
+        self.states.iter()
+    }
+
+    /// Check whether it is possible that no state is selected
+    ///
+    pub fn may_be_none(&self) -> bool {
+        self.none
+    }
+
+    /// Retrieve the outcome of the resolution, if it is definite
+    ///
+    /// If the information available suffices for deciding which state is
+    /// selected, this function yields `Some` with the result which would be
+    /// returned by `Resolvable::issue_state()` for the complete issue.
+    /// Otherwise, `None` is returned.
+    ///
+    pub fn definite(&self) -> Option<Option<&Arc<IssueState<C>>>> {
+        match (self.states.len(), self.none) {
+            (0, _) => Some(None),
+            (1, false) => Some(self.states.first()),
+            _ => None,
+        }
+    }
+
+    /// Retrieve a provisional state
+    ///
+    /// The provisional state is the state with the highest precedence among
+    /// the states which may be selected. If no state may be selected, this
+    /// function yields `None`.
+    ///
+    pub fn provisional(&self) -> Option<&Arc<IssueState<C>>> {
+        self.states.last()
+    }
+}
+
+
+
+
+impl<C> IssueStateSet<C>
+    where C: Condition
+{
+    /// Determine the states which may be selected for a partially known issue
+    ///
+    /// Conditions are evaluated using `Condition::satisfied_by_partial()`. If
+    /// all of them can be decided, the result will be definite and equal to
+    /// the result of `Resolvable::issue_state()`.
+    ///
+    pub fn possible_states(&self, issue: &C::Issue) -> Result<PossibleStates<C>> {
+        self.possible_states_in(issue, &Context::default())
+    }
+
+    /// Determine the states which may be selected for a partially known issue in a given context
+    ///
+    /// Like `possible_states()`, but the conditions are evaluated in the
+    /// context provided using `Condition::satisfied_by_partial_in()`.
+    ///
+    pub fn possible_states_in(
+        &self,
+        issue: &C::Issue,
+        context: &Context
+    ) -> Result<PossibleStates<C>> {
+        let mut enabled_map = BTreeMap::new();
+        let mut states = Vec::default();
+        let mut none = true;
+
+        for state in self.iter() {
+            let mut deps = Vec::default();
+            for (dependency, relation) in state.relations.iter() {
+                if *relation == StateRelation::Extends {
+                    let enabled = enabled_map
+                        .get(dependency)
+                        .cloned()
+                        .ok_or_else(|| Error::from(ErrorKind::DependencyError))?;
+                    deps.push(enabled);
+                }
+            }
+
+            // The state's own conditions only need to be evaluated if the
+            // extended states don't already rule out the state.
+            let deps = all_partial(deps);
+            let enabled = match deps {
+                Some(false) => deps,
+                _ => {
+                    let conditions = state.conditions_satisfied_partial_in(issue, context);
+                    all_partial([deps, conditions].iter().cloned())
+                },
+            };
+
+            // A state which is certainly enabled takes precedence over all
+            // preceding states. A state which may be enabled only adds to the
+            // possibilities.
+            match enabled {
+                Some(true) => {
+                    states.clear();
+                    states.push(state.clone());
+                    none = false;
+                },
+                None => states.push(state.clone()),
+                Some(false) => (),
+            }
+            enabled_map.insert(state, enabled);
+        }
+
+        Ok(PossibleStates {states, none})
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use resolution::Resolvable;
+    use std::collections;
+    use test::example_states;
+
+    fn possible(flags: Vec<(&'static str, bool)>) -> (Vec<String>, bool, Option<Option<String>>) {
+        let issue: collections::BTreeMap<_, _> = flags.into_iter().collect();
+        let states = example_states()
+            .possible_states(&issue)
+            .expect("Failed to determine possible states.");
+        (
+            states.iter().map(|s| s.name().clone()).collect(),
+            states.may_be_none(),
+            states.definite().map(|s| s.map(|s| s.name().clone())),
+        )
+    }
+
+    #[test]
+    fn unknown() {
+        let (states, none, definite) = possible(vec![]);
+        assert_eq!(states, vec!["new", "acknowledged", "assigned", "closed"]);
+        assert!(!none);
+        assert_eq!(definite, None);
+
+        let (states, _, definite) = possible(vec![("acked", true), ("assigned", false)]);
+        assert_eq!(states, vec!["acknowledged", "closed"]);
+        assert_eq!(definite, None);
+
+        let (states, _, definite) = possible(vec![("acked", false)]);
+        assert_eq!(states, vec!["new", "closed"]);
+        assert_eq!(definite, None);
+    }
+
+    #[test]
+    fn definite() {
+        let states = example_states();
+        let issues = vec![
+            vec![("acked", false), ("closed", false)],
+            vec![("acked", true), ("assigned", true), ("closed", false)],
+            vec![("closed", true)],
+        ];
+
+        for flags in issues {
+            let issue: collections::BTreeMap<_, _> = flags.into_iter().collect();
+            let expected = states
+                .issue_state(&issue)
+                .expect("Failed to determine state.")
+                .map(|s| s.name().clone());
+            let possible = states
+                .possible_states(&issue)
+                .expect("Failed to determine possible states.");
+            let definite = possible
+                .definite()
+                .expect("Wrongly determined no definite state.")
+                .map(|s| s.name().clone());
+            assert_eq!(definite, expected);
+            assert_eq!(possible.provisional().map(|s| s.name().clone()), expected);
+        }
+    }
+}
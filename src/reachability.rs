@@ -0,0 +1,643 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Reachability analysis for sets of issue states
+//!
+//! A state which can not be selected for any issue is most likely the result
+//! of a mistake in a specification. This module provides an analysis,
+//! `IssueStateSet::reachability()`, which determines for each state whether it
+//! can be selected for some assignment of metadata. For each state which can
+//! be selected, an example assignment is provided.
+//!
+//! The analysis operates on condition atoms alone. Hence, the conditions need
+//! to implement `ToExpression`, with atoms implementing `ConditionAtom`. Atoms
+//! are interpreted like `MetadataCondition`s. The values considered for each
+//! piece of metadata are derived from the literals the metadata is compared
+//! against, which also determine the values' type. For atoms on numbers,
+//! Boolean values, points in time, durations and versions, these values cover
+//! all relevant cases. Relative points in time are resolved against the
+//! current time of the context the analysis is performed in. For atoms on
+//! strings, in particular ones matching regular expressions, the analysis may
+//! fail to find an assignment for a state which can actually be selected.
+//!
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use condition::{ConditionAtom, MatchOp};
+use context::Context;
+use datetime::format_timestamp;
+use error::*;
+use expression::{Expression, ToExpression};
+use metadata::{Metadata, MetadataCondition, ToValue, Value};
+use resolution::IssueStateSet;
+use state::IssueState;
+use value::Literal;
+use version::{Identifier, Version};
+
+
+
+
+/// Value of a piece of metadata in an example assignment
+///
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExampleValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Timestamp(SystemTime),
+    Duration(Duration),
+    Version(Version),
+}
+
+
+impl ToValue for ExampleValue {
+    fn to_value(&self) -> Value<'_> {
+        match *self {
+            ExampleValue::String(ref s) => Value::String(s.as_str()),
+            ExampleValue::Integer(i) => Value::Integer(i),
+            ExampleValue::Float(f) => Value::Float(f),
+            ExampleValue::Bool(b) => Value::Bool(b),
+            ExampleValue::Timestamp(t) => Value::Timestamp(t),
+            ExampleValue::Duration(d) => Value::Duration(d),
+            ExampleValue::Version(ref v) => Value::Version(v.clone()),
+        }
+    }
+}
+
+
+/// Display an example value
+///
+/// Strings are displayed quoted, in order to distinguish them from values of
+/// other types.
+///
+impl fmt::Display for ExampleValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExampleValue::String(ref s) => write!(f, "{:?}", s),
+            ExampleValue::Integer(i) => i.fmt(f),
+            ExampleValue::Float(v) => v.fmt(f),
+            ExampleValue::Bool(b) => b.fmt(f),
+            ExampleValue::Timestamp(t) => f.write_str(&format_timestamp(t)),
+            ExampleValue::Duration(d) => write!(f, "{}s", d.as_secs()),
+            ExampleValue::Version(ref v) => v.fmt(f),
+        }
+    }
+}
+
+
+/// Assignment of metadata
+///
+/// Metadata not contained in the map is considered absent.
+///
+pub type Assignment = BTreeMap<String, ExampleValue>;
+
+
+
+
+/// Result of the reachability analysis for a single state
+///
+pub struct StateReachability<C> {
+    state: Arc<IssueState<C>>,
+    example: Option<Assignment>,
+}
+
+
+impl<C> StateReachability<C> {
+    /// Retrieve the state analysed
+    ///
+    pub fn state(&self) -> &Arc<IssueState<C>> {
+        &self.state
+    }
+
+    /// Check whether the state can be selected for some issue
+    ///
+    pub fn is_reachable(&self) -> bool {
+        self.example.is_some()
+    }
+
+    /// Retrieve an assignment of metadata for which the state is selected
+    ///
+    /// If no such assignment was found, this function yields `None`.
+    ///
+    pub fn example(&self) -> Option<&Assignment> {
+        self.example.as_ref()
+    }
+}
+
+
+
+
+impl<C> IssueStateSet<C>
+    where C: ToExpression,
+          C::Atom: ConditionAtom
+{
+    /// Determine, for each state, whether it can be selected for some issue
+    ///
+    /// The results are returned in the order in which the states appear in
+    /// the set. A state is considered selected for an assignment if it is the
+    /// state `Resolvable::issue_state()` yields for the assignment.
+    ///
+    /// The search is exhaustive over the values considered. Its cost grows
+    /// exponentially with the number of distinct metadata identifiers which
+    /// need to be assigned for deciding a state's selection.
+    ///
+    pub fn reachability(&self) -> Result<Vec<StateReachability<C>>> {
+        self.reachability_in(&Context::default())
+    }
+
+    /// Determine, for each state, whether it can be selected in a given context
+    ///
+    /// Like `reachability()`, but the conditions are evaluated in the context
+    /// provided. A state is considered selected for an assignment if it is the
+    /// state `Resolvable::issue_state_in()` yields for the assignment.
+    ///
+    pub fn reachability_in(&self, context: &Context) -> Result<Vec<StateReachability<C>>> {
+        let mirror = mirror_states(self)?;
+
+        // Collect the values to consider for each piece of metadata, starting
+        // with its absence.
+        let mut literals: BTreeMap<&str, Vec<(&MatchOp, &str)>> = BTreeMap::new();
+        for state in self.iter() {
+            for condition in state.conditions.iter() {
+                collect_atoms(&condition.to_expression(), &mut |atom| {
+                    literals.entry(atom.name()).or_default().extend(atom.op_val());
+                });
+            }
+        }
+        let domains: Vec<(String, Vec<Option<ExampleValue>>)> = literals
+            .into_iter()
+            .map(|(name, literals)| (name.to_owned(), candidates(&literals, context.now())))
+            .collect();
+
+        self.iter()
+            .zip(mirror.iter())
+            .map(|(state, target)| {
+                let mut partial = PartialAssignment::default();
+                let example = search(&mirror, target, &domains, context, &mut partial)?;
+                Ok(StateReachability {state: state.clone(), example})
+            })
+            .collect()
+    }
+}
+
+
+
+
+/// Assignment of metadata, some of which is not yet decided
+///
+#[derive(Default)]
+struct PartialAssignment {
+    values: Assignment,
+    /// Metadata decided so far, including absent metadata
+    known: BTreeSet<String>,
+}
+
+
+impl Metadata for PartialAssignment {
+    fn get(&self, identifier: &str) -> Option<Value<'_>> {
+        self.values.get(identifier).map(ToValue::to_value)
+    }
+
+    fn known(&self, identifier: &str) -> bool {
+        self.known.contains(identifier)
+    }
+}
+
+
+type MirrorCondition = Expression<MetadataCondition<PartialAssignment>>;
+
+
+/// Create a copy of a set with the conditions replaced by `MetadataCondition`s
+///
+/// The states of the copy appear in the same order as in the original.
+///
+fn mirror_states<C>(states: &IssueStateSet<C>) -> Result<IssueStateSet<MirrorCondition>>
+    where C: ToExpression,
+          C::Atom: ConditionAtom
+{
+    let mut mirrored: BTreeMap<&str, Arc<IssueState<MirrorCondition>>> = BTreeMap::new();
+    let mut data = Vec::default();
+    for state in states.iter() {
+        let mut mirror = IssueState::new(state.name().clone());
+        mirror.conditions = state
+            .conditions
+            .iter()
+            .map(|condition| mirror_expression(&condition.to_expression()))
+            .collect();
+        for (related, relation) in state.relations.iter() {
+            let related = mirrored
+                .get(related.name().as_str())
+                .ok_or_else(|| Error::from(ErrorKind::DependencyError))?;
+            mirror.relations.insert(related.clone(), relation.clone());
+        }
+
+        let mirror = Arc::new(mirror);
+        mirrored.insert(state.name().as_str(), mirror.clone());
+        data.push(mirror);
+    }
+    Ok(data.into())
+}
+
+
+/// Convert an expression over atoms to an expression over `MetadataCondition`s
+///
+fn mirror_expression<A>(expression: &Expression<&A>) -> MirrorCondition
+    where A: ConditionAtom
+{
+    match *expression {
+        Expression::Atom(atom) => {
+            let op_val = atom.op_val().map(|(op, value)| (op.clone(), value));
+            Expression::Atom(MetadataCondition::new(atom.name(), atom.negated(), op_val))
+        },
+        Expression::And(ref e) => Expression::And(e.iter().map(mirror_expression).collect()),
+        Expression::Or(ref e) => Expression::Or(e.iter().map(mirror_expression).collect()),
+        Expression::Not(ref e) => Expression::Not(Box::new(mirror_expression(e))),
+    }
+}
+
+
+/// Call a function for every atom in an expression
+///
+fn collect_atoms<'a, A, F>(expression: &Expression<&'a A>, f: &mut F)
+    where F: FnMut(&'a A)
+{
+    match *expression {
+        Expression::Atom(atom) => f(atom),
+        Expression::And(ref e) | Expression::Or(ref e) => for e in e {
+            collect_atoms(e, f)
+        },
+        Expression::Not(ref e) => collect_atoms(e, f),
+    }
+}
+
+
+/// Derive the values to consider for a piece of metadata
+///
+/// The values are derived from the match operators and literals of the atoms
+/// referring to the metadata. The type of the values is inferred from the
+/// literals: if all of them are integers, only integers are considered, and
+/// likewise for numbers, Boolean values, points in time, durations and
+/// versions. Otherwise, strings are considered. Points in time are resolved
+/// relative to `now`. Absence is always considered first.
+///
+fn candidates(literals: &[(&MatchOp, &str)], now: SystemTime) -> Vec<Option<ExampleValue>> {
+    let parsed: Vec<_> = literals
+        .iter()
+        .map(|&(op, raw)| (op, raw, Literal::from(raw)))
+        .collect();
+    let is_pattern = |op: &MatchOp| *op == MatchOp::Regex || *op == MatchOp::Glob;
+    let all = |f: &dyn Fn(&Literal) -> bool| !parsed.is_empty() && parsed
+        .iter()
+        .all(|&(op, _, ref literal)| !is_pattern(op) && f(literal));
+
+    let mut values = Vec::default();
+    if all(&|l| l.integer().is_some()) {
+        // Integers are considered at, right below and right above each of
+        // the literals.
+        for i in parsed.iter().filter_map(|l| l.2.integer()) {
+            let integers = [i, i.saturating_sub(1), i.saturating_add(1)];
+            values.extend(integers.iter().cloned().map(ExampleValue::Integer));
+        }
+    } else if all(&|l| l.float().is_some()) {
+        // Numbers are considered at, between, below and above the literals.
+        let mut numbers: Vec<f64> = parsed.iter().filter_map(|l| l.2.float()).collect();
+        numbers.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        values.extend(numbers.iter().cloned().map(ExampleValue::Float));
+        values.extend(numbers.windows(2).map(|w| ExampleValue::Float((w[0] + w[1]) / 2.0)));
+        if let (Some(first), Some(last)) = (numbers.first(), numbers.last()) {
+            values.push(ExampleValue::Float(first - 1.0));
+            values.push(ExampleValue::Float(last + 1.0));
+        }
+    } else if all(&|l| l.boolean().is_some()) {
+        values.extend([ExampleValue::Bool(true), ExampleValue::Bool(false)].iter().cloned());
+    } else if all(&|l| l.timestamp_at(now).is_some()) {
+        // Points in time are considered at, a second before and a second
+        // after each of the literals.
+        let second = Duration::from_secs(1);
+        for time in parsed.iter().filter_map(|l| l.2.timestamp_at(now)) {
+            let times = [Some(time), time.checked_sub(second), time.checked_add(second)];
+            values.extend(times.iter().filter_map(|t| *t).map(ExampleValue::Timestamp));
+        }
+    } else if all(&|l| l.duration().is_some()) {
+        // Durations are considered at, a second below and a second above
+        // each of the literals.
+        let second = Duration::from_secs(1);
+        for duration in parsed.iter().filter_map(|l| l.2.duration()) {
+            let durations = [
+                Some(duration),
+                duration.checked_sub(second),
+                duration.checked_add(second),
+            ];
+            values.extend(durations.iter().filter_map(|d| *d).map(ExampleValue::Duration));
+        }
+    } else if all(&|l| l.version().is_some()) {
+        // Versions are considered at, right below and right above each of
+        // the literals.
+        for version in parsed.iter().filter_map(|l| l.2.version()) {
+            values.push(ExampleValue::Version(version.clone()));
+            values.extend(version_below(version).map(ExampleValue::Version));
+            values.push(ExampleValue::Version(version_above(version)));
+        }
+    } else {
+        // Strings are considered equal to, and slightly greater than, the
+        // literals. Patterns are considered with wildcards replaced.
+        for &(op, raw) in literals {
+            let raw = match *op {
+                MatchOp::Glob => raw.replace('*', "").replace('?', "x"),
+                _ => raw.to_owned(),
+            };
+            values.push(ExampleValue::String(format!("{} ", raw)));
+            values.push(ExampleValue::String(raw));
+        }
+        let contained: Vec<_> = literals
+            .iter()
+            .filter(|literal| *literal.0 == MatchOp::Contains)
+            .map(|literal| literal.1)
+            .collect();
+        if contained.len() > 1 {
+            values.push(ExampleValue::String(contained.join(" ")));
+        }
+        values.push(ExampleValue::String("x".to_owned()));
+        values.push(ExampleValue::String(String::new()));
+    }
+
+    let mut retval = vec![None];
+    for value in values {
+        if !retval.iter().any(|v| v.as_ref() == Some(&value)) {
+            retval.push(Some(value));
+        }
+    }
+    retval
+}
+
+
+/// Determine a version right below a given one
+///
+/// For a normal version, this is the lowest pre-release of that version, which
+/// is greater than all versions with a lower version number. For a pre-release,
+/// it is the lowest pre-release or, failing that, a lower normal version.
+///
+fn version_below(version: &Version) -> Option<Version> {
+    let lowest = vec![Identifier::Numeric(0)];
+    if version.pre.is_empty() || version.pre > lowest {
+        let mut below = Version::new(version.major, version.minor, version.patch);
+        below.pre = lowest;
+        return Some(below);
+    }
+
+    match (version.major, version.minor, version.patch) {
+        (0, 0, 0) => None,
+        (major, 0, 0) => Some(Version::new(major - 1, 0, 0)),
+        (major, minor, 0) => Some(Version::new(major, minor - 1, 0)),
+        (major, minor, patch) => Some(Version::new(major, minor, patch - 1)),
+    }
+}
+
+
+/// Determine a version right above a given one
+///
+/// For a normal version, this is the version with the next patch number. For a
+/// pre-release, it is the pre-release with an additional identifier.
+///
+fn version_above(version: &Version) -> Version {
+    if version.pre.is_empty() {
+        Version::new(version.major, version.minor, version.patch.saturating_add(1))
+    } else {
+        let mut above = Version::new(version.major, version.minor, version.patch);
+        above.pre = version.pre.clone();
+        above.pre.push(Identifier::Numeric(0));
+        above
+    }
+}
+
+
+/// Search for an assignment for which a given state is selected
+///
+/// Metadata is assigned in order. After each assignment, the states which may
+/// still be selected are determined, and the search is cut short if the target
+/// is not among them.
+///
+fn search(
+    states: &IssueStateSet<MirrorCondition>,
+    target: &Arc<IssueState<MirrorCondition>>,
+    domains: &[(String, Vec<Option<ExampleValue>>)],
+    context: &Context,
+    partial: &mut PartialAssignment
+) -> Result<Option<Assignment>> {
+    let possible = states.possible_states_in(partial, context)?;
+    match possible.definite() {
+        Some(Some(state)) if Arc::ptr_eq(state, target) => return Ok(Some(partial.values.clone())),
+        Some(_) => return Ok(None),
+        None => (),
+    }
+    if !possible.iter().any(|state| Arc::ptr_eq(state, target)) {
+        return Ok(None);
+    }
+
+    let ((name, values), rest) = match domains.split_first() {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+    partial.known.insert(name.clone());
+    for value in values.iter() {
+        match *value {
+            Some(ref value) => partial.values.insert(name.clone(), value.clone()),
+            None => partial.values.remove(name),
+        };
+        if let Some(example) = search(states, target, rest, context, partial)? {
+            return Ok(Some(example));
+        }
+    }
+    partial.values.remove(name);
+    partial.known.remove(name);
+    Ok(None)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use condition::ConditionFactory;
+    use expression::ExpressionFactory;
+    use metadata::MetadataConditionFactory;
+    use resolution::Resolvable;
+    use std::time::UNIX_EPOCH;
+    use test::example_states;
+
+    type TestCondition = Expression<MetadataCondition<Assignment>>;
+
+    /// Create a set of states from a list of names, atoms and overridden states
+    ///
+    /// Each state overrides the states with the given indices.
+    ///
+    fn states(specs: &[(&str, &[&str], &[usize])]) -> IssueStateSet<TestCondition> {
+        let factory: ExpressionFactory<MetadataConditionFactory<Assignment>> = Default::default();
+        let mut data: Vec<Arc<IssueState<TestCondition>>> = Vec::default();
+        for &(name, atoms, overridden) in specs {
+            let mut state = IssueState::new(name.to_string());
+            state.conditions = atoms
+                .iter()
+                .map(|a| factory.parse_condition(a).expect("Failed to parse condition"))
+                .collect();
+            state.add_overridden(overridden.iter().map(|i| data[*i].clone()));
+            data.push(Arc::new(state));
+        }
+        data.into()
+    }
+
+    /// Check that the examples found actually yield the states analysed
+    ///
+    fn check_examples(states: &IssueStateSet<TestCondition>) -> Vec<bool> {
+        check_examples_in(states, &Context::default())
+    }
+
+    /// Check that the examples found in a context yield the states analysed
+    ///
+    fn check_examples_in(states: &IssueStateSet<TestCondition>, context: &Context) -> Vec<bool> {
+        let reachability = states.reachability_in(context).expect("Failed to analyse states");
+        for result in reachability.iter() {
+            if let Some(example) = result.example() {
+                let state = states
+                    .issue_state_in(example, context)
+                    .expect("Failed to determine state")
+                    .expect("Wrongly determined no state");
+                assert_eq!(state.name(), result.state().name());
+            }
+        }
+        reachability.iter().map(StateReachability::is_reachable).collect()
+    }
+
+    #[test]
+    fn smoke() {
+        let reachability = example_states().reachability().expect("Failed to analyse states");
+        let examples: Vec<_> = reachability
+            .iter()
+            .map(|r| {
+                let keys = r.example().map(|e| e.keys().cloned().collect::<Vec<_>>());
+                (r.state().name().as_str(), keys)
+            })
+            .collect();
+        assert_eq!(examples, vec![
+            ("new", Some(vec![])),
+            ("acknowledged", Some(vec!["acked".to_string()])),
+            ("assigned", Some(vec!["acked".to_string(), "assigned".to_string()])),
+            ("closed", Some(vec!["closed".to_string()])),
+        ]);
+    }
+
+    #[test]
+    fn orderings() {
+        let states = states(&[
+            ("low", &["votes<5"], &[]),
+            ("high", &["votes<10"], &[0]),
+            ("between", &["votes>3", "votes<=4"], &[]),
+            ("none", &["votes>4", "votes<5"], &[]),
+            ("fraction", &["rating>1.5", "rating<1.7"], &[]),
+        ]);
+        assert_eq!(check_examples(&states), vec![false, true, true, false, true]);
+    }
+
+    #[test]
+    fn strings() {
+        let states = states(&[
+            ("open", &[], &[]),
+            ("bug", &["labels~bug", "labels~ui"], &[0]),
+            ("named", &["assignee=jdoe", "assignee<jdoe"], &[0]),
+            ("prefixed", &["branch*=fix-*", "branch>fix"], &[0]),
+            ("confirmed", &["confirmed!=false"], &[0]),
+        ]);
+        assert_eq!(check_examples(&states), vec![true, true, false, true, true]);
+    }
+
+    #[test]
+    fn versions() {
+        let states = states(&[
+            ("open", &[], &[]),
+            ("fixed", &["fixed>=1.4.0", "fixed<1.10.0"], &[0]),
+            ("prerelease", &["fixed>1.10.0-rc.1", "fixed<1.10.0"], &[0]),
+            ("none", &["fixed>1.4.0", "fixed<1.4.1-0"], &[0]),
+        ]);
+        assert_eq!(check_examples(&states), vec![true, true, true, false]);
+    }
+
+    #[test]
+    fn times() {
+        let now = UNIX_EPOCH + Duration::from_secs(1796083200);
+        let context = Context::new(now);
+        let states = states(&[
+            ("open", &[], &[]),
+            ("due", &["due>=now-30d", "due<now"], &[0]),
+            ("scheduled", &["due>2026-12-01", "due<=now+1h"], &[0]),
+            ("none", &["due>now", "due<2026-12-01T00:00:01Z"], &[0]),
+            ("stale", &["age>14d", "age<=P14DT1S"], &[0]),
+        ]);
+        assert_eq!(check_examples_in(&states, &context), vec![true, true, true, false, true]);
+
+        let reachability = states.reachability_in(&context).expect("Failed to analyse states");
+        let example = reachability[1].example().expect("No example found");
+        match example.get("due") {
+            Some(&ExampleValue::Timestamp(due)) => {
+                assert!(due >= now - Duration::from_secs(30 * 86400) && due < now)
+            },
+            due => panic!("Unexpected example {:?}", due),
+        }
+    }
+
+    #[test]
+    fn expressions() {
+        let factory: MetadataConditionFactory<Assignment> = Default::default();
+        let atom = |a| factory
+            .parse_condition(a)
+            .map(Expression::Atom)
+            .expect("Failed to parse condition");
+
+        let mut either = IssueState::new("either".to_string());
+        either.conditions = vec![Expression::Or(vec![atom("foo"), atom("bar")])];
+        let either = Arc::new(either);
+
+        let mut neither = IssueState::new("neither".to_string());
+        let any = Expression::Or(vec![atom("foo"), atom("bar")]);
+        neither.conditions = vec![Expression::Not(Box::new(any))];
+        neither.add_overridden(vec![either.clone()]);
+
+        let mut both = IssueState::new("both".to_string());
+        both.conditions = vec![atom("foo"), Expression::Not(Box::new(atom("!bar")))];
+        both.add_extended(vec![either.clone()]);
+
+        let states: IssueStateSet<_> = vec![either, Arc::new(neither), Arc::new(both)].into();
+        assert_eq!(check_examples(&states), vec![true, true, true]);
+
+        let reachability = states.reachability().expect("Failed to analyse states");
+        let example = reachability[2].example().expect("No example found");
+        assert!(example.contains_key("foo") && example.contains_key("bar"));
+    }
+}
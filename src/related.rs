@@ -0,0 +1,505 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Resolution of states depending on related issues
+//!
+//! Some states depend not only on an issue itself but also on the states of
+//! other issues it references, e.g. an issue may be considered "blocked" as
+//! long as any of its blockers is not resolved. This module provides the
+//! `RelatedCondition` trait for such conditions, which may query the states of
+//! referenced issues while being evaluated.
+//!
+//! The referenced issues are retrieved from an `IssueProvider`. A
+//! `RelatedResolver` resolves the states of the provided issues, recursively
+//! resolving referenced issues on demand. The state of every issue is resolved
+//! at most once. Cyclic references among issues are reported as an error.
+//!
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::Hash;
+use std::result::Result as RResult;
+use std::sync::Arc;
+
+use context::Context;
+use error::*;
+use expression::Expression;
+use resolution::{deps_enabled, EnabledMap, IssueStateSet};
+use state::IssueState;
+
+
+
+
+/// Trait for providing issues by their identifier
+///
+pub trait IssueProvider {
+    type Id;
+    type Issue;
+
+    /// Retrieve the issue with the given identifier
+    ///
+    /// If no such issue exists, this function is expected to yield `None`.
+    ///
+    fn issue(&self, id: &Self::Id) -> Option<&Self::Issue>;
+}
+
+
+impl<K, I> IssueProvider for BTreeMap<K, I>
+    where K: Ord
+{
+    type Id = K;
+    type Issue = I;
+
+    fn issue(&self, id: &Self::Id) -> Option<&Self::Issue> {
+        self.get(id)
+    }
+}
+
+
+impl<K, I> IssueProvider for HashMap<K, I>
+    where K: Hash + Eq
+{
+    type Id = K;
+    type Issue = I;
+
+    fn issue(&self, id: &Self::Id) -> Option<&Self::Issue> {
+        self.get(id)
+    }
+}
+
+
+
+
+/// Access to the states of related issues
+///
+/// An implementation of this trait is passed to conditions during their
+/// evaluation by a `RelatedResolver`.
+///
+pub trait RelatedStates<Id> {
+    /// Retrieve the context in which conditions are evaluated
+    ///
+    fn context(&self) -> &Context;
+
+    /// Retrieve the name of the state of the issue with the given identifier
+    ///
+    /// If no state is enabled for the issue, this function yields `None`. If
+    /// the issue's state can not be resolved, e.g. because the issue does not
+    /// exist or because the issue refers back to an issue currently being
+    /// resolved, an error is returned.
+    ///
+    fn state_of(&mut self, id: &Id) -> Result<Option<String>>;
+}
+
+
+/// Trait for conditions depending on related issues
+///
+/// This is the counterpart of `Condition` for conditions which may query the
+/// states of other issues.
+///
+pub trait RelatedCondition {
+    type Issue;
+    type Id;
+
+    /// Check whether the condition is satisfied by an issue
+    ///
+    /// The states of related issues are retrieved via `related`. Errors
+    /// reported by `related` should be passed on to the caller.
+    ///
+    fn satisfied_with(
+        &self,
+        issue: &Self::Issue,
+        related: &mut dyn RelatedStates<Self::Id>
+    ) -> RResult<bool, BoxError>;
+}
+
+
+/// Evaluation of expressions depending on related issues
+///
+/// Like their unrelated counterpart, conjunctions and disjunctions
+/// short-circuit. Thus, related issues are only resolved if required.
+///
+impl<C> RelatedCondition for Expression<C>
+    where C: RelatedCondition
+{
+    type Issue = C::Issue;
+    type Id = C::Id;
+
+    fn satisfied_with(
+        &self,
+        issue: &Self::Issue,
+        related: &mut dyn RelatedStates<Self::Id>
+    ) -> RResult<bool, BoxError> {
+        match *self {
+            Expression::Atom(ref c) => c.satisfied_with(issue, related),
+            Expression::And(ref e) => {
+                for e in e {
+                    if !e.satisfied_with(issue, related)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            },
+            Expression::Or(ref e) => {
+                for e in e {
+                    if e.satisfied_with(issue, related)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            },
+            Expression::Not(ref e) => e.satisfied_with(issue, related).map(|v| !v),
+        }
+    }
+}
+
+
+
+
+/// Resolver for states depending on related issues
+///
+/// The resolver resolves the states of the issues retrieved from an
+/// `IssueProvider`. Results are memoized: the state of each issue is resolved
+/// at most once during the resolver's lifetime, regardless of whether it is
+/// requested directly or referenced by another issue.
+///
+/// Instances of this type are created via `IssueStateSet::related_resolver()`.
+///
+pub struct RelatedResolver<'a, C, P>
+    where C: RelatedCondition + 'a,
+          P: IssueProvider<Id = C::Id, Issue = C::Issue> + 'a
+{
+    states: &'a IssueStateSet<C>,
+    provider: &'a P,
+    context: Context,
+    /// States of the issues already resolved
+    resolved: BTreeMap<C::Id, Option<Arc<IssueState<C>>>>,
+    /// Issues currently being resolved, in the order of their reference
+    pending: Vec<C::Id>,
+}
+
+
+impl<'a, C, P> RelatedResolver<'a, C, P>
+    where C: RelatedCondition + 'a,
+          C::Id: Ord + Clone + fmt::Display,
+          P: IssueProvider<Id = C::Id, Issue = C::Issue> + 'a
+{
+    /// Resolve the state of the issue with the given identifier
+    ///
+    /// If no state is enabled for the issue, this function yields `None`. If
+    /// the issue can not be retrieved from the provider, an
+    /// `ErrorKind::IssueNotFound` error is returned. If the issue refers,
+    /// directly or indirectly, to itself in the course of its resolution, an
+    /// `ErrorKind::CyclicIssueDependency` error is returned.
+    ///
+    pub fn issue_state(&mut self, id: &C::Id) -> Result<Option<Arc<IssueState<C>>>> {
+        if let Some(state) = self.resolved.get(id) {
+            return Ok(state.clone());
+        }
+
+        if let Some(pos) = self.pending.iter().position(|p| p == id) {
+            let mut cycle: Vec<_> = self.pending[pos..].iter().map(ToString::to_string).collect();
+            cycle.push(id.to_string());
+            return Err(Error::from(ErrorKind::CyclicIssueDependency(cycle)));
+        }
+
+        let issue = self
+            .provider
+            .issue(id)
+            .ok_or_else(|| Error::from(ErrorKind::IssueNotFound(id.to_string())))?;
+
+        self.pending.push(id.clone());
+        let state = self.resolve(issue);
+        self.pending.pop();
+
+        let state = state?;
+        self.resolved.insert(id.clone(), state.clone());
+        Ok(state)
+    }
+
+    /// Resolve the state for a given issue
+    ///
+    /// The issue itself need not be retrievable from the provider and its
+    /// state is not memoized. However, the states of related issues are.
+    ///
+    pub fn resolve(&mut self, issue: &C::Issue) -> Result<Option<Arc<IssueState<C>>>> {
+        // Like `Resolvable::issue_state()`, we select the last enabled state.
+        // Extended states are checked first since resolving related issues
+        // may be expensive.
+        let states = self.states;
+        let mut enabled_map = EnabledMap::default();
+        let mut selected = None;
+        for state in states.iter() {
+            let enabled = deps_enabled(state, &enabled_map)?
+                && self.conditions_satisfied(state, issue)?;
+            if enabled {
+                selected = Some(state);
+            }
+            enabled_map.insert(state.clone(), enabled);
+        }
+        Ok(selected.cloned())
+    }
+
+    /// Check whether all of a state's own conditions are satisfied
+    ///
+    fn conditions_satisfied(&mut self, state: &IssueState<C>, issue: &C::Issue) -> Result<bool> {
+        for (index, condition) in state.conditions.iter().enumerate() {
+            let satisfied = condition.satisfied_with(issue, self).map_err(|e| {
+                let kind = ErrorKind::ConditionFailed {state: state.name().clone(), condition: index};
+                Error::with_source(kind, e)
+            })?;
+            if !satisfied {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+
+impl<'a, C, P> RelatedStates<C::Id> for RelatedResolver<'a, C, P>
+    where C: RelatedCondition + 'a,
+          C::Id: Ord + Clone + fmt::Display,
+          P: IssueProvider<Id = C::Id, Issue = C::Issue> + 'a
+{
+    fn context(&self) -> &Context {
+        &self.context
+    }
+
+    fn state_of(&mut self, id: &C::Id) -> Result<Option<String>> {
+        self.issue_state(id).map(|state| state.map(|s| s.name().clone()))
+    }
+}
+
+
+
+
+impl<C> IssueStateSet<C>
+    where C: RelatedCondition
+{
+    /// Create a resolver for issues depending on related issues
+    ///
+    /// The resolver will resolve the states of issues retrieved from
+    /// `provider`, evaluating conditions in the given context.
+    ///
+    pub fn related_resolver<'a, P>(
+        &'a self,
+        provider: &'a P,
+        context: Context
+    ) -> RelatedResolver<'a, C, P>
+        where P: IssueProvider<Id = C::Id, Issue = C::Issue>
+    {
+        RelatedResolver {
+            states: self,
+            provider,
+            context,
+            resolved: BTreeMap::new(),
+            pending: Vec::new(),
+        }
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::collections::BTreeSet;
+    use std::error::Error as EError;
+
+    struct TestIssue {
+        resolved: bool,
+        blockers: Vec<u32>,
+        duplicate_of: Option<u32>,
+    }
+
+    fn issue(resolved: bool, blockers: Vec<u32>, duplicate_of: Option<u32>) -> TestIssue {
+        TestIssue {resolved, blockers, duplicate_of}
+    }
+
+    enum TestCond {
+        Resolved,
+        Blocked,
+        DuplicateOfResolved,
+    }
+
+    impl RelatedCondition for TestCond {
+        type Issue = TestIssue;
+        type Id = u32;
+
+        fn satisfied_with(
+            &self,
+            issue: &Self::Issue,
+            related: &mut dyn RelatedStates<Self::Id>
+        ) -> RResult<bool, BoxError> {
+            let is_resolved = |state: Option<String>| state.is_some_and(|s| s == "resolved");
+            match *self {
+                TestCond::Resolved => Ok(issue.resolved),
+                TestCond::Blocked => {
+                    for blocker in issue.blockers.iter() {
+                        if !is_resolved(related.state_of(blocker)?) {
+                            return Ok(true);
+                        }
+                    }
+                    Ok(false)
+                },
+                TestCond::DuplicateOfResolved => match issue.duplicate_of {
+                    Some(ref original) => Ok(is_resolved(related.state_of(original)?)),
+                    None => Ok(false),
+                },
+            }
+        }
+    }
+
+    /// Provider counting the number of issues retrieved
+    ///
+    struct CountingProvider {
+        issues: BTreeMap<u32, TestIssue>,
+        retrieved: Cell<usize>,
+    }
+
+    impl IssueProvider for CountingProvider {
+        type Id = u32;
+        type Issue = TestIssue;
+
+        fn issue(&self, id: &Self::Id) -> Option<&Self::Issue> {
+            self.retrieved.set(self.retrieved.get() + 1);
+            self.issues.get(id)
+        }
+    }
+
+    /// Create a set of states "open", "blocked" (overriding "open"), "resolved"
+    /// (overriding "blocked") and "duplicate" (overriding "resolved")
+    ///
+    fn states() -> IssueStateSet<TestCond> {
+        let mut set = BTreeSet::new();
+        let mut previous: Option<Arc<IssueState<TestCond>>> = None;
+        for (name, condition) in [
+            ("open", None),
+            ("blocked", Some(TestCond::Blocked)),
+            ("resolved", Some(TestCond::Resolved)),
+            ("duplicate", Some(TestCond::DuplicateOfResolved)),
+        ] {
+            let mut state = IssueState::new(name.to_string());
+            state.conditions.extend(condition);
+            state.add_overridden(previous.take());
+            let state = Arc::new(state);
+            set.insert(state.clone());
+            previous = Some(state);
+        }
+        IssueStateSet::from_set(set).expect("Failed to create issue state set.")
+    }
+
+    fn state_name(state: Result<Option<Arc<IssueState<TestCond>>>>) -> Option<String> {
+        state.expect("Failed to determine state.").map(|s| s.name().clone())
+    }
+
+    /// Retrieve the kind of the innermost error of the library in a chain
+    ///
+    fn root_kind(error: &Error) -> &ErrorKind {
+        let mut current = error;
+        while let Some(source) = current.source().and_then(|s| s.downcast_ref::<Error>()) {
+            current = source;
+        }
+        current.kind()
+    }
+
+    #[test]
+    fn smoke() {
+        let mut issues = BTreeMap::new();
+        issues.insert(1, issue(true, vec![], None));
+        issues.insert(2, issue(false, vec![1, 3], None));
+        issues.insert(3, issue(false, vec![], None));
+        issues.insert(4, issue(false, vec![], Some(1)));
+        issues.insert(5, issue(false, vec![1], Some(3)));
+
+        let states = states();
+        let mut resolver = states.related_resolver(&issues, Context::default());
+        let mut state = |id| state_name(resolver.issue_state(&id));
+        assert_eq!(state(1).as_deref(), Some("resolved"));
+        assert_eq!(state(2).as_deref(), Some("blocked"));
+        assert_eq!(state(3).as_deref(), Some("open"));
+        assert_eq!(state(4).as_deref(), Some("duplicate"));
+        assert_eq!(state(5).as_deref(), Some("open"));
+
+        let unlisted = issue(false, vec![3], None);
+        let state = state_name(resolver.resolve(&unlisted));
+        assert_eq!(state.as_deref(), Some("blocked"));
+    }
+
+    #[test]
+    fn memoization() {
+        let mut issues = BTreeMap::new();
+        issues.insert(1, issue(true, vec![], None));
+        issues.insert(2, issue(false, vec![1], None));
+        issues.insert(3, issue(false, vec![1], Some(1)));
+        let provider = CountingProvider {issues, retrieved: Cell::new(0)};
+
+        let states = states();
+        let mut resolver = states.related_resolver(&provider, Context::default());
+        for id in &[2, 3, 1, 2] {
+            resolver.issue_state(id).expect("Failed to determine state.");
+        }
+        assert_eq!(provider.retrieved.get(), 3);
+    }
+
+    #[test]
+    fn cycles() {
+        let mut issues = BTreeMap::new();
+        issues.insert(1, issue(false, vec![2], None));
+        issues.insert(2, issue(false, vec![3], None));
+        issues.insert(3, issue(false, vec![], Some(1)));
+
+        let states = states();
+        let mut resolver = states.related_resolver(&issues, Context::default());
+        match resolver.issue_state(&1) {
+            Err(e) => match *root_kind(&e) {
+                ErrorKind::CyclicIssueDependency(ref cycle) => assert_eq!(cycle, &["1", "2", "3", "1"]),
+                _ => panic!("Unexpected error: {}", e),
+            },
+            Ok(_) => panic!("Cycle not detected."),
+        }
+
+        // Failed resolutions are not memoized
+        assert!(resolver.issue_state(&3).is_err());
+    }
+
+    #[test]
+    fn missing_issue() {
+        let mut issues = BTreeMap::new();
+        issues.insert(1, issue(false, vec![7], None));
+
+        let states = states();
+        let mut resolver = states.related_resolver(&issues, Context::default());
+        match resolver.issue_state(&1) {
+            Err(e) => match *root_kind(&e) {
+                ErrorKind::IssueNotFound(ref id) => assert_eq!(id, "7"),
+                _ => panic!("Unexpected error: {}", e),
+            },
+            Ok(_) => panic!("Missing issue not detected."),
+        }
+    }
+}
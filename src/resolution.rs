@@ -34,6 +34,7 @@ use std::slice;
 use std::sync::Arc;
 
 use condition::Condition;
+use context::Context;
 use error::*;
 use iter::LeftJoinable;
 use state;
@@ -43,7 +44,7 @@ use state;
 
 /// Map for tracking enabled and disabled states
 ///
-type EnabledMap<C> = collections::BTreeMap<Arc<state::IssueState<C>>, bool>;
+pub(crate) type EnabledMap<C> = collections::BTreeMap<Arc<state::IssueState<C>>, bool>;
 
 
 /// Check whether the dependencies for an issue's state allow it to be enabled
@@ -55,9 +56,7 @@ type EnabledMap<C> = collections::BTreeMap<Arc<state::IssueState<C>>, bool>;
 /// This function may be used for implementing efficient computation of an
 /// issue's state.
 ///
-fn deps_enabled<C>(state: &state::IssueState<C>, map: &EnabledMap<C>) -> Result<bool>
-    where C: Condition
-{
+pub(crate) fn deps_enabled<C>(state: &state::IssueState<C>, map: &EnabledMap<C>) -> Result<bool> {
     state
         .relations
         .iter()
@@ -66,16 +65,46 @@ fn deps_enabled<C>(state: &state::IssueState<C>, map: &EnabledMap<C>) -> Result<
             state::StateRelation::Extends   => Some(item.1),
             state::StateRelation::Overrides => None,
         })
-        .fold(Some(true), |state, val| if let (Some(s), Some(v)) = (state, val) {
-            Some(s && *v)
-        } else {
-            None
-        })
+        .try_fold(true, |state, val| val.map(|v| state && *v))
         .ok_or_else(|| Error::from(ErrorKind::DependencyError))
-        // TODO: replace with try_fold()
 }
 
 
+/// Check whether a state is enabled, evaluating dependencies on demand
+///
+/// The state's own conditions are evaluated first. Only if they are satisfied,
+/// the extended states are checked, recursively. Results are memoized in
+/// `memo`, which maps states to whether they are enabled.
+///
+fn lazily_enabled<'a, C>(
+    state: &'a state::IssueState<C>,
+    issue: &C::Issue,
+    context: &Context,
+    memo: &mut collections::BTreeMap<&'a state::IssueState<C>, bool>
+) -> Result<bool>
+    where C: Condition
+{
+    if let Some(enabled) = memo.get(state) {
+        return Ok(*enabled);
+    }
+
+    let mut enabled = state.try_conditions_satisfied(issue, context)?;
+    for (dependency, relation) in state.relations.iter() {
+        if !enabled {
+            break;
+        }
+        if *relation == state::StateRelation::Extends {
+            enabled = lazily_enabled(dependency, issue, context, memo)?;
+        }
+    }
+
+    memo.insert(state, enabled);
+    Ok(enabled)
+}
+
+
+
+
 /// Trait providing operation for resolving issues' states
 ///
 /// Implementations of trait provide the reesolution of an issue's state. It is
@@ -83,6 +112,10 @@ fn deps_enabled<C>(state: &state::IssueState<C>, map: &EnabledMap<C>) -> Result<
 /// for the resolution. For example, this may be implemented for containers of
 /// issue states.
 ///
+/// Implementations need to provide at least one of `issue_state()` and
+/// `issue_state_in()`, since each defaults to the other. Implementations
+/// honoring the context should provide `issue_state_in()`.
+///
 pub trait Resolvable<C>
     where C: Condition
 {
@@ -93,9 +126,35 @@ pub trait Resolvable<C>
     /// enabled for the issue.
     ///
     /// If no state is enabled for the given issue, this function will yield
-    /// `None`.
+    /// `None`. If the evaluation of a condition fails, an
+    /// `ErrorKind::ConditionFailed` error is returned.
+    ///
+    /// Conditions are evaluated in a default `Context`, e.g. relative to the
+    /// current time as reported by the system clock.
+    ///
+    fn issue_state(&self, issue: &C::Issue) -> Result<Option<Arc<state::IssueState<C>>>> {
+        self.issue_state_in(issue, &Context::default())
+    }
+
+    /// Resolve the state for a given issue in a given context
+    ///
+    /// This function yields the state selected for the issue like
+    /// `issue_state()`. However, the conditions are evaluated in the context
+    /// provided, which allows resolving states deterministically, e.g. for a
+    /// fixed point in time.
     ///
-    fn issue_state(&self, issue: &C::Issue) -> Result<Option<Arc<state::IssueState<C>>>>;
+    /// The default implementation ignores the context and defers to
+    /// `issue_state()`, which allows implementations predating the context to
+    /// remain valid.
+    ///
+    fn issue_state_in(
+        &self,
+        issue: &C::Issue,
+        context: &Context
+    ) -> Result<Option<Arc<state::IssueState<C>>>> {
+        let _ = context;
+        self.issue_state(issue)
+    }
 }
 
 
@@ -106,9 +165,7 @@ pub trait Resolvable<C>
 /// This set of issue states is intended for the efficient computation of an
 /// issue's state.
 ///
-pub struct IssueStateSet<C>
-    where C: Condition
-{
+pub struct IssueStateSet<C> {
     /// Container of states
     ///
     /// The states are kept in a linear sequence, ordered by dependency:
@@ -120,9 +177,7 @@ pub struct IssueStateSet<C>
 }
 
 
-impl<C> IssueStateSet<C>
-    where C: Condition
-{
+impl<C> IssueStateSet<C> {
     /// Create an issue state set from a orderd set of issue states
     ///
     /// # Note:
@@ -130,7 +185,8 @@ impl<C> IssueStateSet<C>
     /// The set provided must be the (transitive) closure of all its elements
     /// regarding its relations to other sets: if a state is in the set, all
     /// states related to it must also be in the set. No explicit checking is
-    /// performed to assert this property.
+    /// performed to assert this property. Use `validate()` for detecting
+    /// relations to states not in the set.
     ///
     pub fn from_set(mut states: collections::BTreeSet<Arc<state::IssueState<C>>>) -> Result<Self> {
         // We generate the state set by transferring states from the origin set
@@ -171,32 +227,196 @@ impl<C> IssueStateSet<C>
     ///
     /// This iterator will yield an issue state only after all its dependencies.
     ///
-    pub fn iter(&self) -> slice::Iter<Arc<state::IssueState<C>>> {
+    pub fn iter(&self) -> slice::Iter<'_, Arc<state::IssueState<C>>> {
         self.data.iter()
     }
 }
 
 
-impl<C> Resolvable<C> for IssueStateSet<C>
+impl<C> IssueStateSet<C>
     where C: Condition
 {
-    fn issue_state(&self, issue: &C::Issue) -> Result<Option<Arc<state::IssueState<C>>>> {
-        let mut retval = None;
+    /// Determine all states enabled for a given issue
+    ///
+    /// The states are yielded in the same order as they appear in the set,
+    /// e.g. a state appears only after all its dependencies.
+    ///
+    pub fn enabled_states(&self, issue: &C::Issue) -> Result<state::IssueStateVec<C>> {
+        self.enabled_states_in(issue, &Context::default())
+    }
+
+    /// Determine all states enabled for a given issue in a given context
+    ///
+    /// Like `enabled_states()`, but the conditions are evaluated in the
+    /// context provided.
+    ///
+    pub fn enabled_states_in(
+        &self,
+        issue: &C::Issue,
+        context: &Context
+    ) -> Result<state::IssueStateVec<C>> {
+        let enabled_map = self.enabled_map(issue, context)?;
+        Ok(self.enabled_iter(&enabled_map).map(Clone::clone).collect())
+    }
+
+    /// Determine the maximal states enabled for a given issue
+    ///
+    /// A maximal enabled state is an enabled state which is neither extended
+    /// nor overridden by another enabled state. As the relations are
+    /// transitive, a state which is extended or overridden by a disabled state
+    /// which is, in turn, extended or overridden by an enabled state is also
+    /// not considered maximal.
+    ///
+    /// For a well-formed set of issue states, at most one maximal state should
+    /// be enabled for any given issue.
+    ///
+    pub fn maximal_enabled_states(&self, issue: &C::Issue) -> Result<state::IssueStateVec<C>> {
+        self.maximal_enabled_states_in(issue, &Context::default())
+    }
+
+    /// Determine the maximal states enabled for a given issue in a given context
+    ///
+    /// Like `maximal_enabled_states()`, but the conditions are evaluated in
+    /// the context provided.
+    ///
+    pub fn maximal_enabled_states_in(
+        &self,
+        issue: &C::Issue,
+        context: &Context
+    ) -> Result<state::IssueStateVec<C>> {
+        let enabled_map = self.enabled_map(issue, context)?;
+
+        // Walking the states in reverse order, we mark all states extended or
+        // overridden by an enabled state as "covered". Since dependencies
+        // always appear before the states depending on them, a state will be
+        // marked before we visit it.
+        let mut covered = collections::BTreeSet::new();
+        let mut retval = Vec::default();
+        for state in self.data.iter().rev() {
+            let enabled = enabled_map.get(state).cloned().unwrap_or(false);
+            let is_covered = covered.contains(state);
+            if enabled && !is_covered {
+                retval.push(state.clone());
+            }
+            if enabled || is_covered {
+                covered.extend(state.relations.keys());
+            }
+        }
+
+        retval.reverse();
+        Ok(retval)
+    }
+
+    /// Resolve the state for a given issue, failing on ambiguities
+    ///
+    /// Like `Resolvable::issue_state()`, this function yields the state
+    /// selected for the given issue. However, if more than one maximal state
+    /// is enabled for the issue, e.g. states which are not related to each
+    /// other, an `ErrorKind::AmbiguousState` error is returned instead of
+    /// silently selecting one of them.
+    ///
+    pub fn issue_state_strict(&self, issue: &C::Issue) -> Result<Option<Arc<state::IssueState<C>>>> {
+        self.issue_state_strict_in(issue, &Context::default())
+    }
+
+    /// Resolve the state for a given issue in a given context, failing on ambiguities
+    ///
+    /// Like `issue_state_strict()`, but the conditions are evaluated in the
+    /// context provided.
+    ///
+    pub fn issue_state_strict_in(
+        &self,
+        issue: &C::Issue,
+        context: &Context
+    ) -> Result<Option<Arc<state::IssueState<C>>>> {
+        let mut maximal = self.maximal_enabled_states_in(issue, context)?;
+        if maximal.len() > 1 {
+            let names = maximal.iter().map(|s| s.name().clone()).collect();
+            return Err(Error::from(ErrorKind::AmbiguousState(names)));
+        }
+        Ok(maximal.pop())
+    }
+
+    /// Resolve the state for a given issue, evaluating conditions lazily
+    ///
+    /// This function yields the same state as `Resolvable::issue_state()`.
+    /// However, the states are visited in reverse order, e.g. the overriding
+    /// states first, and the first enabled state found is selected. Conditions
+    /// of states preceding that state are not evaluated at all, unless they
+    /// are inherited. Thus, this function is preferable if conditions are
+    /// expensive to evaluate.
+    ///
+    /// # Note:
+    ///
+    /// Contrary to `Resolvable::issue_state()`, this function will not detect
+    /// extended states which are not part of the set.
+    ///
+    pub fn issue_state_lazy(&self, issue: &C::Issue) -> Result<Option<Arc<state::IssueState<C>>>> {
+        self.issue_state_lazy_in(issue, &Context::default())
+    }
+
+    /// Resolve the state for a given issue in a given context, evaluating conditions lazily
+    ///
+    /// Like `issue_state_lazy()`, but the conditions are evaluated in the
+    /// context provided.
+    ///
+    pub fn issue_state_lazy_in(
+        &self,
+        issue: &C::Issue,
+        context: &Context
+    ) -> Result<Option<Arc<state::IssueState<C>>>> {
+        let mut memo = collections::BTreeMap::new();
+        for state in self.data.iter().rev() {
+            if lazily_enabled(state, issue, context, &mut memo)? {
+                return Ok(Some(state.clone()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Compute the `EnabledMap` for a given issue
+    ///
+    fn enabled_map(&self, issue: &C::Issue, context: &Context) -> Result<EnabledMap<C>> {
         let mut enabled_map = EnabledMap::default();
 
         // Since the data is nicely ordered in `data`, one liear pass over the
-        // states is sufficient for selecting one for any given issue. We simply
-        // determine the state foe each one as we go and keep the last of the
-        // enabled states.
+        // states is sufficient for determining whether any of the states is
+        // enabled.
         for state in self.data.iter() {
-            let enabled = state.conditions_satisfied(issue)
-                && deps_enabled(&state, &enabled_map)?;
+            let enabled = state.try_conditions_satisfied(issue, context)?
+                && deps_enabled(state, &enabled_map)?;
             enabled_map.insert(state.clone(), enabled);
-            if enabled {
-                retval = Some(state);
-            }
         }
 
+        Ok(enabled_map)
+    }
+
+    /// Get an iterator over the states enabled according to an `EnabledMap`
+    ///
+    fn enabled_iter<'a>(
+        &'a self,
+        enabled_map: &'a EnabledMap<C>
+    ) -> impl DoubleEndedIterator<Item = &'a Arc<state::IssueState<C>>> + 'a {
+        self.data
+            .iter()
+            .filter(move |state| enabled_map.get(*state).cloned().unwrap_or(false))
+    }
+}
+
+
+impl<C> Resolvable<C> for IssueStateSet<C>
+    where C: Condition
+{
+    fn issue_state_in(
+        &self,
+        issue: &C::Issue,
+        context: &Context
+    ) -> Result<Option<Arc<state::IssueState<C>>>> {
+        // The selected state is the last of the enabled states. Since the data
+        // is ordered by dependency, no enabled state following it may extend or
+        // override it.
+        let enabled_map = self.enabled_map(issue, context)?;
+        let retval = self.enabled_iter(&enabled_map).next_back();
         Ok(retval.map(Clone::clone))
     }
 }
@@ -209,9 +429,7 @@ impl<C> Resolvable<C> for IssueStateSet<C>
 /// Within the vector, the states must appear ordered by dependency: all
 /// dependencies of a state must appear before the state itself!
 ///
-impl<C> From<state::IssueStateVec<C>> for IssueStateSet<C>
-    where C: Condition
-{
+impl<C> From<state::IssueStateVec<C>> for IssueStateSet<C> {
     fn from(states: Vec<Arc<state::IssueState<C>>>) -> Self {
         Self {data: states.into_boxed_slice()}
     }
@@ -219,9 +437,7 @@ impl<C> From<state::IssueStateVec<C>> for IssueStateSet<C>
 
 
 // Because #[derive(Default)] doesn't work for some reason
-impl<C> Default for IssueStateSet<C>
-    where C: Condition
-{
+impl<C> Default for IssueStateSet<C> {
     fn default() -> Self {
         Self {data: Default::default()}
     }
@@ -233,9 +449,10 @@ impl<C> Default for IssueStateSet<C>
 #[cfg(test)]
 mod tests {
     use super::*;
-    use test::TestState;
+    use test::{example_states, TestCond, TestState};
 
     #[test]
+    #[allow(array_into_iter, clippy::into_iter_on_ref)]
     fn smoke() {
         let state1 : Arc<TestState> = state::IssueState::new("new".to_string()).into();
 
@@ -319,5 +536,272 @@ mod tests {
             assert_eq!(state.name(), "closed");
         }
     }
-}
 
+    #[test]
+    fn enabled_states() {
+        let states = example_states();
+
+        let names = |states: state::IssueStateVec<TestCond>| states
+            .iter()
+            .map(|s| s.name().clone())
+            .collect::<Vec<_>>();
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("acked", true);
+        issue.insert("closed", true);
+
+        let enabled = states.enabled_states(&issue).expect("Failed to determine states.");
+        assert_eq!(names(enabled), vec!["new", "acknowledged", "closed"]);
+
+        let maximal = states.maximal_enabled_states(&issue).expect("Failed to determine states.");
+        assert_eq!(names(maximal), vec!["closed"]);
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("assigned", true);
+
+        let enabled = states.enabled_states(&issue).expect("Failed to determine states.");
+        assert_eq!(names(enabled), vec!["new"]);
+    }
+
+    #[test]
+    fn maximal_states() {
+        let state1 : Arc<TestState> = state::IssueState::new("new".to_string()).into();
+
+        let state2 : Arc<TestState> = {
+            let mut tmp = state::IssueState::new("labeled".to_string());
+            tmp.conditions = vec!["labeled".into()];
+            tmp.add_overridden(vec![state1.clone()]);
+            tmp
+        }.into();
+
+        let state3 : Arc<TestState> = {
+            let mut tmp = state::IssueState::new("assigned".to_string());
+            tmp.conditions = vec!["assigned".into()];
+            tmp.add_overridden(vec![state1.clone()]);
+            tmp
+        }.into();
+
+        let states : IssueStateSet<TestCond> = vec![state1, state2, state3].into();
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("labeled", true);
+        let state = states
+            .issue_state_strict(&issue)
+            .expect("Failed to determine state.")
+            .expect("Wrongly determined no state.");
+        assert_eq!(state.name(), "labeled");
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("labeled", true);
+        issue.insert("assigned", true);
+
+        let maximal = states.maximal_enabled_states(&issue).expect("Failed to determine states.");
+        assert_eq!(
+            maximal.iter().map(|s| s.name().as_str()).collect::<Vec<_>>(),
+            vec!["labeled", "assigned"]
+        );
+    }
+
+    #[test]
+    fn strict_resolution() {
+        let states = example_states();
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("acked", true);
+        issue.insert("closed", true);
+        let state = states
+            .issue_state_strict(&issue)
+            .expect("Failed to determine state.")
+            .expect("Wrongly determined no state.");
+        assert_eq!(state.name(), "closed");
+
+        let state1 : Arc<TestState> = state::IssueState::new("new".to_string()).into();
+        let state2 : Arc<TestState> = {
+            let mut tmp = state::IssueState::new("labeled".to_string());
+            tmp.conditions = vec!["labeled".into()];
+            tmp
+        }.into();
+        let states : IssueStateSet<TestCond> = vec![state1, state2].into();
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("labeled", true);
+        let err = states
+            .issue_state_strict(&issue)
+            .err()
+            .expect("Ambiguity not detected.");
+        match *err.kind() {
+            ErrorKind::AmbiguousState(ref names) => assert_eq!(*names, vec!["new", "labeled"]),
+            _ => panic!("Unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn lazy_resolution() {
+        let states = example_states();
+        let flags = ["acked", "assigned", "closed"];
+
+        for bits in 0..(1 << flags.len()) {
+            let issue: collections::BTreeMap<_, _> = flags
+                .iter()
+                .enumerate()
+                .map(|(i, flag)| (*flag, bits & (1 << i) != 0))
+                .collect();
+
+            let expected = states.issue_state(&issue).expect("Failed to determine state.");
+            let lazy = states.issue_state_lazy(&issue).expect("Failed to determine state.");
+            assert_eq!(lazy.map(|s| s.name().clone()), expected.map(|s| s.name().clone()));
+        }
+    }
+
+    #[test]
+    fn lazy_evaluation() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        struct CountingCond(TestCond, Rc<Cell<usize>>);
+
+        impl Condition for CountingCond {
+            type Issue = <TestCond as Condition>::Issue;
+
+            fn satisfied_by(&self, issue: &Self::Issue) -> bool {
+                self.1.set(self.1.get() + 1);
+                self.0.satisfied_by(issue)
+            }
+        }
+
+        let count = Rc::new(Cell::new(0));
+        let cond = |name: &'static str| CountingCond(name.into(), count.clone());
+
+        let new : Arc<state::IssueState<CountingCond>> = state::IssueState::new("new".to_string()).into();
+        let acked : Arc<state::IssueState<CountingCond>> = {
+            let mut tmp = state::IssueState::new("acknowledged".to_string());
+            tmp.conditions = vec![cond("acked")];
+            tmp.add_overridden(vec![new.clone()]);
+            tmp
+        }.into();
+        let assigned : Arc<state::IssueState<CountingCond>> = {
+            let mut tmp = state::IssueState::new("assigned".to_string());
+            tmp.conditions = vec![cond("assigned")];
+            tmp.add_extended(vec![acked.clone()]);
+            tmp
+        }.into();
+        let closed : Arc<state::IssueState<CountingCond>> = {
+            let mut tmp = state::IssueState::new("closed".to_string());
+            tmp.conditions = vec![cond("closed")];
+            tmp.add_overridden(vec![assigned.clone()]);
+            tmp
+        }.into();
+        let states: IssueStateSet<_> = vec![new, acked, assigned, closed].into();
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("acked", true);
+        issue.insert("assigned", true);
+        issue.insert("closed", true);
+
+        let state = states
+            .issue_state(&issue)
+            .expect("Failed to determine state.")
+            .expect("Wrongly determined no state.");
+        assert_eq!(state.name(), "closed");
+        assert_eq!(count.get(), 3);
+
+        count.set(0);
+        let state = states
+            .issue_state_lazy(&issue)
+            .expect("Failed to determine state.")
+            .expect("Wrongly determined no state.");
+        assert_eq!(state.name(), "closed");
+        assert_eq!(count.get(), 1);
+
+        // The extended state's conditions must be evaluated, but only once
+        issue.insert("closed", false);
+        count.set(0);
+        let state = states
+            .issue_state_lazy(&issue)
+            .expect("Failed to determine state.")
+            .expect("Wrongly determined no state.");
+        assert_eq!(state.name(), "assigned");
+        assert_eq!(count.get(), 3);
+    }
+
+    #[test]
+    fn failing_conditions() {
+        use error::BoxError;
+        use std::error::Error as EError;
+        use std::result::Result as RResult;
+        use test::TestCond;
+
+        // Condition failing if the issue lacks the flag
+        struct StrictCond(TestCond);
+
+        impl Condition for StrictCond {
+            type Issue = <TestCond as Condition>::Issue;
+
+            fn satisfied_by(&self, issue: &Self::Issue) -> bool {
+                self.0.satisfied_by(issue)
+            }
+
+            fn try_satisfied_by(&self, issue: &Self::Issue) -> RResult<bool, BoxError> {
+                let name = self.0.to_string();
+                issue
+                    .get(name.as_str())
+                    .cloned()
+                    .ok_or_else(|| format!("no value for {}", name).into())
+            }
+        }
+
+        let new : Arc<state::IssueState<StrictCond>> = state::IssueState::new("new".to_string()).into();
+        let acked : Arc<state::IssueState<StrictCond>> = {
+            let mut tmp = state::IssueState::new("acknowledged".to_string());
+            tmp.conditions = vec![StrictCond("acked".into()), StrictCond("valid".into())];
+            tmp.add_overridden(vec![new.clone()]);
+            tmp
+        }.into();
+        let states: IssueStateSet<_> = vec![new, acked].into();
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("acked", true);
+
+        let err = match states.issue_state(&issue) {
+            Ok(_) => panic!("Failed to report failure."),
+            Err(err) => err,
+        };
+        match *err.kind() {
+            ErrorKind::ConditionFailed {ref state, condition} => {
+                assert_eq!(state, "acknowledged");
+                assert_eq!(condition, 1);
+            },
+            _ => panic!("Unexpected error: {}", err),
+        }
+        assert_eq!(err.source().expect("Missing source.").to_string(), "no value for valid");
+        assert!(states.issue_state_lazy(&issue).is_err());
+        assert!(states.enabled_states(&issue).is_err());
+
+        // Conditions following an unsatisfied one are not evaluated
+        issue.insert("acked", false);
+        let state = states
+            .issue_state(&issue)
+            .expect("Failed to determine state.")
+            .expect("Wrongly determined no state.");
+        assert_eq!(state.name(), "new");
+    }
+
+    #[test]
+    fn legacy_resolvable() {
+        // Implementation providing only the resolution without a context
+        struct Fixed(Arc<TestState>);
+
+        impl Resolvable<TestCond> for Fixed {
+            fn issue_state(&self, _: &<TestCond as Condition>::Issue) -> Result<Option<Arc<TestState>>> {
+                Ok(Some(self.0.clone()))
+            }
+        }
+
+        let resolvable = Fixed(state::IssueState::new("new".to_string()).into());
+        let state = resolvable
+            .issue_state_in(&collections::BTreeMap::new(), &Context::default())
+            .expect("Failed to determine state.")
+            .expect("Wrongly determined no state.");
+        assert_eq!(state.name(), "new");
+    }
+}
@@ -32,7 +32,9 @@ use std::collections::BTreeMap;
 use std::cmp::Ordering;
 use std::sync::Arc;
 
-use condition::Condition;
+use condition::{all_partial, Condition};
+use context::Context;
+use error::*;
 
 
 
@@ -72,9 +74,7 @@ pub type StateRelations<C> = BTreeMap<Arc<IssueState<C>>, StateRelation>;
 /// related to other issues. Those relations affect whether a state is selected
 /// by a resolver for a given issue, provided that it is enabled for saif issue.
 ///
-pub struct IssueState<C>
-    where C: Condition + Sized
-{
+pub struct IssueState<C> {
     /// The name of the state
     name: String,
     /// Metadata conditions of the state
@@ -84,14 +84,12 @@ pub struct IssueState<C>
 }
 
 
-impl<C> IssueState<C>
-    where C: Condition + Sized
-{
+impl<C> IssueState<C> {
     /// Create an issue state with a given name
     ///
     pub fn new(name: String) -> Self {
         Self {
-            name: name,
+            name,
             conditions: Vec::new(),
             relations: StateRelations::new(),
         }
@@ -124,7 +122,12 @@ impl<C> IssueState<C>
             .map(|state| (state, StateRelation::Overrides));
         self.relations.extend(entries)
     }
+}
+
 
+impl<C> IssueState<C>
+    where C: Condition
+{
     /// Check whether all conditions of the state are satisfied for an issue
     ///
     /// # Note:
@@ -136,35 +139,88 @@ impl<C> IssueState<C>
     pub fn conditions_satisfied(&self, issue: &C::Issue) -> bool {
         self.conditions.iter().all(|c| c.satisfied_by(issue))
     }
+
+    /// Check whether all conditions of the state are satisfied for a partially known issue
+    ///
+    /// This function yields `None` if it can not be decided whether the
+    /// conditions are satisfied. The conditions are evaluated using
+    /// `Condition::satisfied_by_partial()`.
+    ///
+    pub fn conditions_satisfied_partial(&self, issue: &C::Issue) -> Option<bool> {
+        all_partial(self.conditions.iter().map(|c| c.satisfied_by_partial(issue)))
+    }
+
+    /// Check whether all conditions are satisfied for a partially known issue in a given context
+    ///
+    /// Like `conditions_satisfied_partial()`, but the conditions are evaluated
+    /// in the context provided using `Condition::satisfied_by_partial_in()`.
+    ///
+    pub fn conditions_satisfied_partial_in(
+        &self,
+        issue: &C::Issue,
+        context: &Context
+    ) -> Option<bool> {
+        all_partial(self.conditions.iter().map(|c| c.satisfied_by_partial_in(issue, context)))
+    }
+
+    /// Check whether all conditions of the state are satisfied, failing on errors
+    ///
+    /// Like `conditions_satisfied()`, this function checks the state's own
+    /// conditions. However, the conditions are evaluated in the given context
+    /// using `Condition::try_satisfied_in()`. If the evaluation of a condition
+    /// fails, an `ErrorKind::ConditionFailed` error identifying the state and
+    /// the condition is returned.
+    ///
+    pub fn try_conditions_satisfied(&self, issue: &C::Issue, context: &Context) -> Result<bool> {
+        for index in 0..self.conditions.len() {
+            if !self.try_condition_satisfied(index, issue, context)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Check whether a single condition of the state is satisfied
+    ///
+    /// The condition is identified by its index. Failures are reported like
+    /// for `try_conditions_satisfied()`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `index` is out of bounds.
+    ///
+    pub fn try_condition_satisfied(
+        &self,
+        index: usize,
+        issue: &C::Issue,
+        context: &Context
+    ) -> Result<bool> {
+        self.conditions[index].try_satisfied_in(issue, context).map_err(|e| {
+            let kind = ErrorKind::ConditionFailed {state: self.name.clone(), condition: index};
+            Error::with_source(kind, e)
+        })
+    }
 }
 
 
-impl<C> PartialEq for IssueState<C>
-    where C: Condition
-{
+impl<C> PartialEq for IssueState<C> {
     fn eq(&self, other: &Self) -> bool {
         self.name == other.name
     }
 }
 
 
-impl<C> Eq for IssueState<C>
-    where C: Condition
-{}
+impl<C> Eq for IssueState<C> {}
 
 
-impl<C> PartialOrd for IssueState<C>
-    where C: Condition
-{
+impl<C> PartialOrd for IssueState<C> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
     }
 }
 
 
-impl<C> Ord for IssueState<C>
-    where C: Condition
-{
+impl<C> Ord for IssueState<C> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.name.cmp(&other.name)
     }
@@ -23,18 +23,20 @@
 // SOFTWARE.
 //
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fmt;
 use std::result::Result as RResult;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use condition;
 use error;
+use resolution::IssueStateSet;
 use state;
 
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Hash, Debug)]
 pub struct TestCond {
     name: String,
 }
@@ -51,6 +53,45 @@ impl condition::Condition for TestCond {
     fn satisfied_by(&self, issue: &Self::Issue) -> bool {
         issue.get(self.name.as_str()).cloned().unwrap_or(false)
     }
+
+    fn satisfied_by_partial(&self, issue: &Self::Issue) -> Option<bool> {
+        issue.get(self.name.as_str()).cloned()
+    }
+}
+
+impl condition::ConditionAtom for TestCond {
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn negated(&self) -> bool {
+        false
+    }
+
+    fn op_val(&self) -> Option<(&condition::MatchOp, &str)> {
+        None
+    }
+}
+
+impl ::expression::ToExpression for TestCond {
+    type Atom = Self;
+
+    fn to_expression(&self) -> ::expression::Expression<&Self> {
+        ::expression::Expression::Atom(self)
+    }
+}
+
+#[cfg(feature = "yaml-rust")]
+impl ::yaml::ToYaml for TestCond {
+    fn to_yaml(&self) -> ::yaml_rust::Yaml {
+        ::yaml_rust::Yaml::String(self.name.clone())
+    }
+}
+
+impl fmt::Display for TestCond {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name.as_str())
+    }
 }
 
 impl FromStr for TestCond {
@@ -62,17 +103,19 @@ impl FromStr for TestCond {
 }
 
 
+#[cfg(any(feature = "yaml-rust", feature = "json", feature = "toml"))]
 #[derive(Default)]
 pub struct TestCondFactory {}
 
+#[cfg(any(feature = "yaml-rust", feature = "json", feature = "toml"))]
 impl condition::ConditionFactory<TestCond> for TestCondFactory {
     type Error = TestCondParseError;
 
     fn make_condition(
         &self,
         name: &str,
-        neg: bool,
-        val_op: Option<(condition::MatchOp, &str)>
+        _neg: bool,
+        _val_op: Option<(condition::MatchOp, &str)>
     ) -> RResult<TestCond, TestCondParseError> {
         Ok(TestCond { name: name.to_owned() })
     }
@@ -104,3 +147,41 @@ impl From<error::Error> for TestCondParseError {
 
 pub type TestState = state::IssueState<TestCond>;
 
+
+
+/// Create the example set of states also used in the documentation
+///
+/// The set consists of the states "new", "acknowledged" (overriding "new"),
+/// "assigned" (extending "acknowledged") and "closed" (overriding "assigned").
+///
+pub fn example_states() -> IssueStateSet<TestCond> {
+    let new : Arc<TestState> = state::IssueState::new("new".to_string()).into();
+
+    let acked : Arc<TestState> = {
+        let mut tmp = state::IssueState::new("acknowledged".to_string());
+        tmp.conditions = vec!["acked".into()];
+        tmp.add_overridden(vec![new.clone()]);
+        tmp
+    }.into();
+
+    let assigned : Arc<TestState> = {
+        let mut tmp = state::IssueState::new("assigned".to_string());
+        tmp.conditions = vec!["assigned".into()];
+        tmp.add_extended(vec![acked.clone()]);
+        tmp
+    }.into();
+
+    let closed : Arc<TestState> = {
+        let mut tmp = state::IssueState::new("closed".to_string());
+        tmp.conditions = vec!["closed".into()];
+        tmp.add_overridden(vec![assigned.clone()]);
+        tmp
+    }.into();
+
+    let mut set = BTreeSet::new();
+    set.insert(new);
+    set.insert(acked);
+    set.insert(assigned);
+    set.insert(closed);
+    IssueStateSet::from_set(set).expect("Failed to create issue state set.")
+}
@@ -0,0 +1,226 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Parse issue states from a TOML document
+//!
+//! The primary function for parsing issue states is `parse_issue_states()`. The
+//! states are expected to appear in an array of tables with the key "state".
+//! Each table must contain:
+//! * a "name" entry denoting the name of the state,
+//! * an optional "conditions" entry containing conditions, as an array of
+//!   strings or tables composing conditions via "all", "any" or "not"
+//!   entries,
+//! * an optional "overrides" entry containing an array of state names
+//!   apprearing _prior_ to the current issue state in the array of tables,
+//!   and
+//! * an optional "extends" entry containing an array of state names
+//!   apprearing _prior_ to the current issue state in the array of tables.
+//!
+//! Single strings may be used in place of arrays containing only one item. All
+//! other entries of the document are ignored. For example:
+//!
+//! ```toml
+//! [[state]]
+//! name = "new"
+//!
+//! [[state]]
+//! name = "assigned"
+//! conditions = "assignee"
+//! overrides = "new"
+//! ```
+//!
+
+use std::fmt;
+use std::result::Result as RResult;
+use serde::de::{self, Deserializer as _, IgnoredAny, MapAccess, Visitor};
+use toml_crate::de::{Deserializer, Error};
+
+use condition;
+use de::IssueStatesSeed;
+use resolution::IssueStateSet;
+
+
+
+
+/// Parser specific result type
+///
+/// Errors carry the position at which they occured, which is accessible via
+/// `Error::line_col()`. Note that both line and column are zero-based.
+///
+pub type ParseResult<T> = RResult<T, Error>;
+
+
+/// Parse issue states from a TOML document
+///
+/// The function expects the `deserializer` used for parsing the document as
+/// well as a `cond_factory` for creating the user-provided condition type.
+///
+/// If the document does not contain any states, an empty set is returned.
+///
+pub fn parse_issue_states<C, F>(
+    deserializer: &mut Deserializer,
+    cond_factory: F
+) -> ParseResult<IssueStateSet<C>>
+    where F: condition::ConditionFactory<C>,
+{
+    deserializer.deserialize_map(DocumentVisitor {
+        states: IssueStatesSeed::new(&cond_factory),
+    })
+}
+
+
+/// Parse issue states from a string containing a TOML document
+///
+pub fn from_str<C, F>(s: &str, cond_factory: F) -> ParseResult<IssueStateSet<C>>
+    where F: condition::ConditionFactory<C>,
+{
+    parse_issue_states(&mut Deserializer::new(s), cond_factory)
+}
+
+
+/// Visitor for the top-level table of a document
+///
+struct DocumentVisitor<'f, C, F>
+    where F: 'f
+{
+    states: IssueStatesSeed<'f, C, F>,
+}
+
+
+impl<'de, 'f, C, F> Visitor<'de> for DocumentVisitor<'f, C, F>
+    where F: condition::ConditionFactory<C> + 'f
+{
+    type Value = IssueStateSet<C>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("table containing an array of states")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> RResult<Self::Value, A::Error>
+        where A: MapAccess<'de>
+    {
+        let mut retval = None;
+        let mut states = Some(self.states);
+
+        while let Some(key) = map.next_key::<String>()? {
+            if key != "state" {
+                map.next_value::<IgnoredAny>()?;
+                continue;
+            }
+
+            let seed = states.take().ok_or_else(|| de::Error::duplicate_field("state"))?;
+            retval = Some(map.next_value_seed(seed)?);
+        }
+
+        Ok(retval.unwrap_or_default())
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use state;
+    use test::{TestCond, TestCondFactory};
+
+    // Convenience function for encapsulating boilerplate for each test
+    //
+    fn parse(s: &str) -> IssueStateSet<TestCond> {
+        from_str(s, TestCondFactory::default()).expect("Failed to parse document")
+    }
+
+    #[test]
+    fn empty_document() {
+        let result = parse("");
+        assert_eq!(result.iter().count(), 0);
+    }
+
+    #[test]
+    fn multiple_with_conditions() {
+        let result = parse(r#"
+title = "Workflow"
+
+[[state]]
+name = "new"
+
+[[state]]
+name = "acknowledged"
+conditions = "acked"
+overrides = "new"
+
+[[state]]
+name = "assigned"
+conditions = ["assigned"]
+extends = "acknowledged"
+
+[[state]]
+name = "closed"
+conditions = ["closed"]
+overrides = ["new", "acknowledged", "assigned"]
+"#);
+
+        let mut iter = result.iter();
+
+        let state1 = iter
+            .next()
+            .expect("Parse result does not contain expected state.");
+        assert_eq!(state1.name(), "new");
+
+        let state2 = iter
+            .next()
+            .expect("Parse result does not contain expected state.");
+        assert_eq!(state2.name(), "acknowledged");
+        assert_eq!(state2.conditions, vec!["acked".into()]);
+        assert_eq!(state2.relations.get(state1), Some(&state::StateRelation::Overrides));
+
+        let state3 = iter
+            .next()
+            .expect("Parse result does not contain expected state.");
+        assert_eq!(state3.name(), "assigned");
+        assert_eq!(state3.conditions, vec!["assigned".into()]);
+        assert_eq!(state3.relations.get(state2), Some(&state::StateRelation::Extends));
+
+        let state4 = iter
+            .next()
+            .expect("Parse result does not contain expected state.");
+        assert_eq!(state4.name(), "closed");
+        assert_eq!(state4.conditions, vec!["closed".into()]);
+        assert_eq!(state4.relations.get(state1), Some(&state::StateRelation::Overrides));
+        assert_eq!(state4.relations.get(state2), Some(&state::StateRelation::Overrides));
+        assert_eq!(state4.relations.get(state3), Some(&state::StateRelation::Overrides));
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn error_position() {
+        let err = from_str("[[state]]\nname = \"new\"\n\n[[state]]\nname = \"closed\"\noverrides = \"open\"\n", TestCondFactory::default())
+            .err()
+            .expect("Unknown state not detected");
+        assert_eq!(err.line_col().map(|(line, _)| line), Some(3));
+    }
+}
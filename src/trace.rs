@@ -0,0 +1,298 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Tracing of state resolution
+//!
+//! This module provides means for explaining why a specific state was (or was
+//! not) selected for a given issue. A `ResolutionTrace` records, for every
+//! state in an `IssueStateSet`, which of its conditions failed, which extended
+//! states prevented it from being enabled and which enabled states took
+//! precedence over it.
+//!
+
+use std::collections::BTreeMap;
+use std::slice;
+use std::sync::Arc;
+
+use condition::Condition;
+use context::Context;
+use error::*;
+use resolution::IssueStateSet;
+use state::{IssueState, StateRelation};
+
+
+
+
+/// Trace of the resolution of a single state
+///
+pub struct StateTrace<'a, C>
+    where C: Condition + 'a
+{
+    /// The state this trace refers to
+    pub state: &'a Arc<IssueState<C>>,
+    /// The state's own conditions which are not satisfied by the issue
+    pub failed_conditions: Vec<&'a C>,
+    /// Extended states which are not enabled for the issue
+    ///
+    /// If any extended state is not enabled, this state can not be enabled
+    /// either, regardless of its own conditions.
+    pub blocked_by: Vec<&'a Arc<IssueState<C>>>,
+    /// Enabled states which extend or override this state
+    ///
+    /// The relation may be indirect, via states which are not enabled. This
+    /// list is empty for states which are not enabled themselves.
+    pub overridden_by: Vec<&'a Arc<IssueState<C>>>,
+}
+
+
+impl<'a, C> StateTrace<'a, C>
+    where C: Condition + 'a
+{
+    /// Check whether all of the state's own conditions are satisfied
+    ///
+    pub fn conditions_satisfied(&self) -> bool {
+        self.failed_conditions.is_empty()
+    }
+
+    /// Check whether the state is enabled for the issue
+    ///
+    pub fn enabled(&self) -> bool {
+        self.conditions_satisfied() && self.blocked_by.is_empty()
+    }
+}
+
+
+
+
+/// Trace of the resolution of an issue's state
+///
+/// The trace contains a `StateTrace` for every state in the `IssueStateSet` it
+/// was created from, in the same order: a state's trace appears only after the
+/// traces of all its dependencies.
+///
+pub struct ResolutionTrace<'a, C>
+    where C: Condition + 'a
+{
+    states: Vec<StateTrace<'a, C>>,
+}
+
+
+impl<'a, C> ResolutionTrace<'a, C>
+    where C: Condition + 'a
+{
+    /// Get an iterator over the traces of the individual states
+    ///
+    pub fn iter(&self) -> slice::Iter<'_, StateTrace<'a, C>> {
+        self.states.iter()
+    }
+
+    /// Retrieve the trace for a specific state
+    ///
+    pub fn get(&self, state: &IssueState<C>) -> Option<&StateTrace<'a, C>> {
+        self.states.iter().find(|trace| trace.state.as_ref() == state)
+    }
+
+    /// Retrieve the state selected for the issue
+    ///
+    /// The state selected is the one `Resolvable::issue_state()` would yield.
+    ///
+    pub fn selected(&self) -> Option<&'a Arc<IssueState<C>>> {
+        self.states
+            .iter()
+            .rev()
+            .find(|trace| trace.enabled())
+            .map(|trace| trace.state)
+    }
+}
+
+
+
+
+impl<C> IssueStateSet<C>
+    where C: Condition
+{
+    /// Resolve the state for a given issue, recording a trace
+    ///
+    /// Contrary to `Resolvable::issue_state()`, this function evaluates every
+    /// condition of every state, in order to report all of the failing ones.
+    ///
+    pub fn trace(&self, issue: &C::Issue) -> Result<ResolutionTrace<'_, C>> {
+        self.trace_in(issue, &Context::default())
+    }
+
+    /// Resolve the state for a given issue in a given context, recording a trace
+    ///
+    /// Like `trace()`, but the conditions are evaluated in the context
+    /// provided.
+    ///
+    pub fn trace_in(&self, issue: &C::Issue, context: &Context) -> Result<ResolutionTrace<'_, C>> {
+        let mut states: Vec<StateTrace<C>> = Vec::default();
+        let mut indices = BTreeMap::new();
+
+        for state in self.iter() {
+            let mut failed_conditions = Vec::default();
+            for (index, condition) in state.conditions.iter().enumerate() {
+                if !state.try_condition_satisfied(index, issue, context)? {
+                    failed_conditions.push(condition);
+                }
+            }
+
+            let mut blocked_by = Vec::default();
+            for (dependency, relation) in state.relations.iter() {
+                if *relation == StateRelation::Extends {
+                    let index = *indices
+                        .get(dependency)
+                        .ok_or_else(|| Error::from(ErrorKind::DependencyError))?;
+                    let dependency: &StateTrace<C> = &states[index];
+                    if !dependency.enabled() {
+                        blocked_by.push(dependency.state);
+                    }
+                }
+            }
+
+            indices.insert(state, states.len());
+            states.push(StateTrace {
+                state,
+                failed_conditions,
+                blocked_by,
+                overridden_by: Vec::default(),
+            });
+        }
+
+        // Only after we know which states are enabled, we can tell which ones
+        // are overridden by other enabled states. Like for the maximal enabled
+        // states, we walk the states in reverse order, propagating the states
+        // covering a disabled state to the states it is related to.
+        for index in (0..states.len()).rev() {
+            let covering = if states[index].enabled() {
+                vec![states[index].state]
+            } else {
+                states[index].overridden_by.clone()
+            };
+
+            for dependency in states[index].state.relations.keys() {
+                if let Some(dependency) = indices.get(dependency).map(|i| &mut states[*i]) {
+                    for state in covering.iter() {
+                        if !dependency.overridden_by.iter().any(|s| Arc::ptr_eq(s, state)) {
+                            dependency.overridden_by.push(state);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Only enabled states lose against overriding ones
+        for state in states.iter_mut().filter(|s| !s.enabled()) {
+            state.overridden_by.clear();
+        }
+
+        Ok(ResolutionTrace {states})
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use resolution::Resolvable;
+    use std::collections;
+    use test::example_states;
+
+    #[test]
+    fn smoke() {
+        let states = example_states();
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("assigned", true);
+        issue.insert("closed", false);
+
+        let trace = states.trace(&issue).expect("Failed to trace resolution.");
+        assert_eq!(trace.iter().count(), 4);
+
+        let selected = trace.selected().expect("Wrongly determined no state.");
+        assert_eq!(selected.name(), "new");
+        assert!(
+            Some(selected) == states.issue_state(&issue).expect("Failed to determine state.").as_ref()
+        );
+
+        let new = trace.get(selected).expect("No trace for selected state.");
+        assert!(new.enabled());
+        assert!(new.overridden_by.is_empty());
+
+        let acked = trace.iter().find(|t| t.state.name() == "acknowledged").unwrap();
+        assert!(!acked.conditions_satisfied());
+        assert_eq!(acked.failed_conditions, vec![&"acked".into()]);
+
+        let assigned = trace.iter().find(|t| t.state.name() == "assigned").unwrap();
+        assert!(assigned.conditions_satisfied());
+        assert!(!assigned.enabled());
+        assert_eq!(assigned.blocked_by.len(), 1);
+        assert_eq!(assigned.blocked_by[0].name(), "acknowledged");
+    }
+
+    #[test]
+    fn overridden() {
+        let states = example_states();
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("acked", true);
+        issue.insert("assigned", true);
+
+        let trace = states.trace(&issue).expect("Failed to trace resolution.");
+        assert_eq!(trace.selected().map(|s| s.name().as_str()), Some("assigned"));
+
+        let acked = trace.iter().find(|t| t.state.name() == "acknowledged").unwrap();
+        assert!(acked.enabled());
+        assert_eq!(acked.overridden_by.len(), 1);
+        assert_eq!(acked.overridden_by[0].name(), "assigned");
+
+        let new = trace.iter().find(|t| t.state.name() == "new").unwrap();
+        assert_eq!(new.overridden_by.len(), 1);
+        assert_eq!(new.overridden_by[0].name(), "acknowledged");
+    }
+
+    #[test]
+    fn overridden_transitively() {
+        let states = example_states();
+
+        let mut issue = collections::BTreeMap::new();
+        issue.insert("closed", true);
+
+        let trace = states.trace(&issue).expect("Failed to trace resolution.");
+        assert_eq!(trace.selected().map(|s| s.name().as_str()), Some("closed"));
+        let maximal = states.maximal_enabled_states(&issue).expect("Failed to determine states.");
+        assert_eq!(maximal.iter().map(|s| s.name().as_str()).collect::<Vec<_>>(), vec!["closed"]);
+
+        let new = trace.iter().find(|t| t.state.name() == "new").unwrap();
+        assert!(new.enabled());
+        assert_eq!(new.overridden_by.len(), 1);
+        assert_eq!(new.overridden_by[0].name(), "closed");
+
+        let assigned = trace.iter().find(|t| t.state.name() == "assigned").unwrap();
+        assert!(!assigned.enabled());
+        assert!(assigned.overridden_by.is_empty());
+    }
+}
@@ -0,0 +1,469 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Static consistency checks for sets of issue states
+//!
+//! An `IssueStateSet` may be well-formed, e.g. free of dependency cycles, and
+//! still not behave as intended. This module provides a validation pass,
+//! `IssueStateSet::validate()`, which detects common mistakes and reports them
+//! as `Diagnostic`s.
+//!
+//! The checks concerning conditions operate on condition atoms. Hence, the
+//! conditions need to implement `ToExpression`, with atoms implementing
+//! `ConditionAtom`. The checks are conservative: a contradiction is only
+//! reported if it does not depend on the semantics of any match operator.
+//!
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use condition::{ConditionAtom, MatchOp};
+use expression::{Expression, ToExpression};
+use resolution::IssueStateSet;
+use state::{IssueState, StateRelation};
+
+
+
+
+/// Diagnostic reported by the validation of an `IssueStateSet`
+///
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Diagnostic {
+    /// Multiple states in the set share the same name
+    ///
+    DuplicateName(String),
+    /// A state is related to a state which is not part of the set
+    ///
+    MissingRelation {state: String, related: String},
+    /// A relation of a state is implied by its other relations
+    ///
+    /// This diagnostic is advisory: the redundant relation does not affect the
+    /// resolution. An `Overrides` relation is redundant if the related state is also
+    /// reachable through other relations. An `Extends` relation is redundant
+    /// only if the related state is also reachable through other `Extends`
+    /// relations, since conditions are inherited only via the latter.
+    ///
+    RedundantRelation {state: String, related: String},
+    /// A state can never be selected
+    ///
+    /// The state is extended or overridden, directly or indirectly, by a
+    /// state which is enabled regardless of the issue, e.g. because neither
+    /// it nor any of the states it extends has any conditions.
+    ///
+    Shadowed {state: String, by: String},
+    /// The conditions of a state, including inherited ones, are contradictory
+    ///
+    /// The metadata identifier on which conflicting conditions are imposed is
+    /// included.
+    ///
+    Contradiction {state: String, identifier: String},
+}
+
+
+impl Diagnostic {
+    /// Retrieve the name of the state the diagnostic refers to
+    ///
+    pub fn state(&self) -> &str {
+        match *self {
+            Diagnostic::DuplicateName(ref state) => state,
+            Diagnostic::MissingRelation {ref state, ..} => state,
+            Diagnostic::RedundantRelation {ref state, ..} => state,
+            Diagnostic::Shadowed {ref state, ..} => state,
+            Diagnostic::Contradiction {ref state, ..} => state,
+        }
+    }
+
+    /// Check whether the diagnostic indicates an error
+    ///
+    /// Errors indicate sets which do not behave as intended, e.g. states which
+    /// can never be selected. Other diagnostics are merely advisory.
+    ///
+    pub fn is_error(&self) -> bool {
+        !matches!(*self, Diagnostic::RedundantRelation {..})
+    }
+}
+
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Diagnostic::DuplicateName(ref state) =>
+                write!(f, "multiple states named '{}'", state),
+            Diagnostic::MissingRelation {ref state, ref related} => write!(
+                f,
+                "state '{}' is related to '{}', which is not part of the set",
+                state,
+                related
+            ),
+            Diagnostic::RedundantRelation {ref state, ref related} => write!(
+                f,
+                "relation of state '{}' to '{}' is implied by its other relations",
+                state,
+                related
+            ),
+            Diagnostic::Shadowed {ref state, ref by} => write!(
+                f,
+                "state '{}' is never selected since the unconditional state '{}' takes precedence",
+                state,
+                by
+            ),
+            Diagnostic::Contradiction {ref state, ref identifier} => write!(
+                f,
+                "conditions of state '{}' on '{}' contradict each other",
+                state,
+                identifier
+            ),
+        }
+    }
+}
+
+
+
+
+impl<C> IssueStateSet<C>
+    where C: ToExpression,
+          C::Atom: ConditionAtom
+{
+    /// Check the set for inconsistencies
+    ///
+    /// This function reports duplicate state names, relations to states not
+    /// in the set, redundant relations, states which can never be selected
+    /// and states with contradictory conditions. An empty list is returned if
+    /// no issues were found.
+    ///
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut retval = Vec::default();
+
+        let mut names = BTreeMap::new();
+        for state in self.iter() {
+            *names.entry(state.name().as_str()).or_insert(0) += 1;
+        }
+        retval.extend(names
+            .iter()
+            .filter(|entry| *entry.1 > 1)
+            .map(|entry| Diagnostic::DuplicateName(entry.0.to_string())));
+
+        for state in self.iter() {
+            for related in state.relations.keys() {
+                if !names.contains_key(related.name().as_str()) {
+                    retval.push(Diagnostic::MissingRelation {
+                        state: state.name().clone(),
+                        related: related.name().clone(),
+                    });
+                }
+            }
+
+            retval.extend(redundant_relations(state).into_iter().map(|related| {
+                let state = state.name().clone();
+                Diagnostic::RedundantRelation {state, related: related.to_owned()}
+            }));
+
+            if let Some(identifier) = contradiction(state) {
+                retval.push(Diagnostic::Contradiction {
+                    state: state.name().clone(),
+                    identifier: identifier.to_owned(),
+                });
+            }
+        }
+
+        // Every state related to an unconditional state, directly or
+        // indirectly, is shadowed by it.
+        let mut shadowed = BTreeMap::new();
+        for state in self.iter().filter(|state| unconditional(state)) {
+            let mut related = BTreeSet::new();
+            related_closure(state, false, &mut related);
+            for name in related {
+                shadowed.entry(name).or_insert_with(|| state.name().as_str());
+            }
+        }
+        retval.extend(self.iter().filter_map(|state| shadowed
+            .get(state.name().as_str())
+            .map(|by| Diagnostic::Shadowed {state: state.name().clone(), by: by.to_string()})));
+
+        retval
+    }
+}
+
+
+
+
+/// Collect the names of all states transitively related to a given state
+///
+/// If `extends_only` is set, only `Extends` relations are followed.
+///
+fn related_closure<'a, C>(
+    state: &'a IssueState<C>,
+    extends_only: bool,
+    names: &mut BTreeSet<&'a str>
+) {
+    for (related, relation) in state.relations.iter() {
+        if extends_only && *relation != StateRelation::Extends {
+            continue;
+        }
+        if names.insert(related.name().as_str()) {
+            related_closure(related, extends_only, names);
+        }
+    }
+}
+
+
+/// Determine the relations of a state implied by its other relations
+///
+fn redundant_relations<C>(state: &IssueState<C>) -> Vec<&str> {
+    state
+        .relations
+        .iter()
+        .filter(|&(target, relation)| {
+            let extends = *relation == StateRelation::Extends;
+            state.relations.iter().any(|(other, other_relation)| {
+                if other == target || (extends && *other_relation != StateRelation::Extends) {
+                    return false;
+                }
+                let mut names = BTreeSet::new();
+                related_closure(other, extends, &mut names);
+                names.contains(target.name().as_str())
+            })
+        })
+        .map(|(target, _)| target.name().as_str())
+        .collect()
+}
+
+
+/// Check whether a state is enabled regardless of the issue
+///
+fn unconditional<C>(state: &IssueState<C>) -> bool {
+    state.conditions.is_empty() && state
+        .relations
+        .iter()
+        .all(|(related, relation)| *relation != StateRelation::Extends || unconditional(related))
+}
+
+
+/// Find contradictory conditions of a state, including inherited ones
+///
+/// If the conditions impose contradicting requirements on a piece of metadata,
+/// the metadata identifier is returned.
+///
+fn contradiction<C>(state: &IssueState<C>) -> Option<&str>
+    where C: ToExpression,
+          C::Atom: ConditionAtom
+{
+    let mut states = vec![state];
+    let mut index = 0;
+    while let Some(current) = states.get(index).cloned() {
+        states.extend(current
+            .relations
+            .iter()
+            .filter(|entry| *entry.1 == StateRelation::Extends)
+            .map(|entry| entry.0.as_ref()));
+        index += 1;
+    }
+
+    let expressions: Vec<_> = states
+        .iter()
+        .flat_map(|state| state.conditions.iter().map(ToExpression::to_expression))
+        .collect();
+    let mut requirements = Vec::default();
+    for expression in expressions.iter() {
+        required_atoms(expression, true, &mut requirements);
+    }
+
+    // For each atom, we consider whether the relation it expresses, ignoring
+    // the negation, must hold. A relation holding implies the presence of the
+    // piece of metadata.
+    let requirements: Vec<_> = requirements
+        .into_iter()
+        .map(|(atom, value)| (atom.name(), atom.op_val(), value != atom.negated()))
+        .collect();
+    for (i, &(name, op_val, holds)) in requirements.iter().enumerate() {
+        let conflicting = requirements[i + 1..]
+            .iter()
+            .filter(|requirement| requirement.0 == name)
+            .any(|&(_, other_op_val, other_holds)| if op_val == other_op_val {
+                holds != other_holds
+            } else {
+                requires_absence(op_val, holds) && other_holds
+                    || requires_absence(other_op_val, other_holds) && holds
+            });
+        if conflicting {
+            return Some(name);
+        }
+    }
+    None
+}
+
+
+/// Check whether an atom's requirement implies the absence of the metadata
+///
+fn requires_absence(op_val: Option<(&MatchOp, &str)>, holds: bool) -> bool {
+    op_val.is_none() && !holds
+}
+
+
+/// Collect the atoms of an expression whose value is implied by the expression's value
+///
+/// For an expression to evaluate to `value`, each atom collected must evaluate
+/// to the value it is paired with.
+///
+fn required_atoms<'a, A>(
+    expression: &Expression<&'a A>,
+    value: bool,
+    atoms: &mut Vec<(&'a A, bool)>
+) {
+    match *expression {
+        Expression::Atom(atom) => atoms.push((atom, value)),
+        Expression::And(ref e) if value || e.len() == 1 => for e in e {
+            required_atoms(e, value, atoms)
+        },
+        Expression::Or(ref e) if !value || e.len() == 1 => for e in e {
+            required_atoms(e, value, atoms)
+        },
+        Expression::Not(ref e) => required_atoms(e, !value, atoms),
+        _ => (),
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use test::{example_states, TestCond};
+
+    type TestExpr = Expression<TestCond>;
+
+    fn atom(name: &'static str) -> TestExpr {
+        Expression::Atom(TestCond::from(name))
+    }
+
+    fn not(expression: TestExpr) -> TestExpr {
+        Expression::Not(Box::new(expression))
+    }
+
+    fn state(
+        name: &str,
+        conditions: Vec<TestExpr>,
+        extends: &[&Arc<IssueState<TestExpr>>],
+        overrides: &[&Arc<IssueState<TestExpr>>]
+    ) -> Arc<IssueState<TestExpr>> {
+        let mut state = IssueState::new(name.to_string());
+        state.conditions = conditions;
+        state.add_extended(extends.iter().map(|s| (*s).clone()));
+        state.add_overridden(overrides.iter().map(|s| (*s).clone()));
+        Arc::new(state)
+    }
+
+    #[test]
+    fn consistent() {
+        assert_eq!(example_states().validate(), vec![]);
+    }
+
+    #[test]
+    fn names_and_relations() {
+        let foreign = state("foreign", vec![], &[], &[]);
+        let a = state("a", vec![atom("foo")], &[], &[]);
+        let b = state("b", vec![atom("bar")], &[], &[&foreign]);
+        let a_dup = state("a", vec![atom("baz")], &[], &[]);
+
+        let states: IssueStateSet<_> = vec![a, a_dup, b].into();
+        let diagnostics = states.validate();
+        assert_eq!(diagnostics, vec![
+            Diagnostic::DuplicateName("a".to_string()),
+            Diagnostic::MissingRelation {state: "b".to_string(), related: "foreign".to_string()},
+        ]);
+        assert!(diagnostics.iter().all(Diagnostic::is_error));
+    }
+
+    #[test]
+    fn redundant_relations() {
+        let a = state("a", vec![atom("foo")], &[], &[]);
+        let b = state("b", vec![atom("bar")], &[&a], &[]);
+        let c = state("c", vec![atom("baz")], &[&a, &b], &[]);
+        let d = state("d", vec![atom("baz")], &[&b], &[&a]);
+        let e = state("e", vec![atom("baz")], &[&a], &[&b]);
+
+        let states: IssueStateSet<_> = vec![a, b, c, d, e].into();
+        let diagnostics = states.validate();
+        assert_eq!(diagnostics, vec![
+            Diagnostic::RedundantRelation {state: "c".to_string(), related: "a".to_string()},
+            Diagnostic::RedundantRelation {state: "d".to_string(), related: "a".to_string()},
+        ]);
+        assert!(diagnostics.iter().all(|d| !d.is_error()));
+    }
+
+    #[test]
+    fn shadowed() {
+        let triage = state("triage", vec![atom("new")], &[], &[]);
+        let open = state("open", vec![], &[], &[&triage]);
+        let closed = state("closed", vec![atom("closed")], &[], &[&open]);
+
+        let states: IssueStateSet<_> = vec![triage, open, closed].into();
+        assert_eq!(states.validate(), vec![
+            Diagnostic::Shadowed {state: "triage".to_string(), by: "open".to_string()},
+        ]);
+    }
+
+    #[test]
+    fn contradictions() {
+        let a = state("a", vec![atom("foo")], &[], &[]);
+        let b = state("b", vec![not(atom("foo"))], &[&a], &[]);
+        let c = state("c", vec![not(Expression::Or(vec![atom("bar"), atom("foo")]))], &[&a], &[]);
+        let d = state("d", vec![Expression::Or(vec![atom("bar"), not(atom("foo"))])], &[&a], &[]);
+        let e = state("e", vec![not(atom("foo"))], &[], &[&a]);
+
+        let states: IssueStateSet<_> = vec![a, b, c, d, e].into();
+        assert_eq!(states.validate(), vec![
+            Diagnostic::Contradiction {state: "b".to_string(), identifier: "foo".to_string()},
+            Diagnostic::Contradiction {state: "c".to_string(), identifier: "foo".to_string()},
+        ]);
+    }
+
+    #[test]
+    fn metadata_contradictions() {
+        use metadata::{MetadataCondition, MetadataConditionFactory};
+        use condition::ConditionFactory;
+        use std::collections::BTreeMap;
+
+        let factory: MetadataConditionFactory<BTreeMap<String, String>> = Default::default();
+        let conditions = |atoms: &[&str]| -> Vec<MetadataCondition<_>> {
+            atoms
+                .iter()
+                .map(|a| factory.parse_condition(a).expect("Failed to parse condition"))
+                .collect()
+        };
+        let check = |atoms: &[&str]| {
+            let mut state = IssueState::new("state".to_string());
+            state.conditions = conditions(atoms);
+            contradiction(&state).map(ToOwned::to_owned)
+        };
+
+        assert_eq!(check(&["!assignee", "assignee=jdoe"]), Some("assignee".to_string()));
+        assert_eq!(check(&["status=open", "status!=open"]), Some("status".to_string()));
+        assert_eq!(check(&["!assignee", "assignee!=jdoe"]), None);
+        assert_eq!(check(&["status=open", "status=closed"]), None);
+    }
+}
@@ -0,0 +1,601 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Typed values and their comparison to literals
+//!
+//! This module provides the `Value` type, representing a typed piece of
+//! metadata, and the `Literal` type, representing the value of a condition
+//! atom. A `Value` may be matched against a `Literal` using any `MatchOp`,
+//! which allows implementing conditions without reimplementing the parsing of
+//! literals and the semantics of the match operators.
+//!
+//! The literal is interpreted according to the value's type:
+//! * strings are compared lexicographically and a string "contains" the
+//!   literal if it is a substring,
+//! * integers and floating point numbers are compared numerically to the
+//!   literal parsed as a number,
+//! * booleans are only equal or unequal to the literal parsed as a boolean,
+//! * timestamps are compared to the literal, interpreted as an ISO-8601 date
+//!   or date-time, a point in time relative to the current time (e.g.
+//!   `now-14d`) or as seconds since the Unix epoch,
+//! * durations are compared to the literal, interpreted as an ISO-8601
+//!   duration or a sequence of numbers with units (e.g. `1h30m`),
+//! * semantic versions are compared to the literal parsed as a version,
+//!   according to their precedence, and
+//! * lists only support "contains", which holds if any of the list's items is
+//!   equal to the literal.
+//!
+//! Regular expressions (`=~`) and glob patterns (`*=`) are matched against
+//! strings and the string items of lists. A list matches a pattern if any of
+//! its items does. Regular expressions may match any part of the string while
+//! glob patterns, in which `*` matches any sequence of characters and `?`
+//! matches any single character, must match the entire string. Regular
+//! expressions are only supported if the library is compiled with the `regex`
+//! feature enabled.
+//!
+//! The values of each type form a partially ordered set. Hence, `<=` and `>=`
+//! hold if either `<` or `>`, respectively, or `=` hold. Except for strings and
+//! lists, "contains" is equivalent to equality. If a relation is not defined
+//! for a type, or if the literal cannot be interpreted as a value of the type,
+//! the relation never holds.
+//!
+
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, SystemTime};
+
+use condition::MatchOp;
+use datetime::{epoch_offset, parse_duration, TimeLiteral};
+use error::*;
+use version::Version;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+
+
+
+/// Typed value, e.g. of a piece of metadata
+///
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value<'a> {
+    String(&'a str),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    List(Vec<Value<'a>>),
+    Timestamp(SystemTime),
+    Duration(Duration),
+    Version(Version),
+}
+
+
+impl<'a> Value<'a> {
+    /// Compare the value to a literal
+    ///
+    /// This function yields the ordering of the value relative to the literal,
+    /// interpreted as a value of the same type. If the two are not comparable,
+    /// e.g. because the literal can not be interpreted as a value of the type,
+    /// `None` is returned.
+    ///
+    /// Literals denoting points in time relative to the current time are
+    /// interpreted using the system clock.
+    ///
+    pub fn compare(&self, literal: &Literal) -> Option<Ordering> {
+        self.compare_at(literal, SystemTime::now())
+    }
+
+    /// Compare the value to a literal, given the current time
+    ///
+    /// Like `compare()`, but points in time relative to the current time are
+    /// interpreted relative to `now`.
+    ///
+    pub fn compare_at(&self, literal: &Literal, now: SystemTime) -> Option<Ordering> {
+        match *self {
+            Value::String(s) => Some(s.cmp(literal.raw())),
+            Value::Integer(i) => match literal.integer() {
+                Some(l) => Some(i.cmp(&l)),
+                None => literal.float().and_then(|l| (i as f64).partial_cmp(&l)),
+            },
+            Value::Float(f) => literal.float().and_then(|l| f.partial_cmp(&l)),
+            Value::Bool(b) => literal.boolean().and_then(|l| if b == l {
+                Some(Ordering::Equal)
+            } else {
+                None
+            }),
+            Value::List(_) => None,
+            Value::Timestamp(t) => literal.timestamp_at(now).map(|l| t.cmp(&l)),
+            Value::Duration(d) => literal.duration().map(|l| d.cmp(&l)),
+            Value::Version(ref v) => literal.version().map(|l| v.cmp(l)),
+        }
+    }
+
+    /// Match the value against a literal, using a given operator
+    ///
+    pub fn matches(&self, op: &MatchOp, literal: &Literal) -> bool {
+        self.matches_at(op, literal, SystemTime::now())
+    }
+
+    /// Match the value against a literal, given the current time
+    ///
+    /// Like `matches()`, but points in time relative to the current time are
+    /// interpreted relative to `now`.
+    ///
+    pub fn matches_at(&self, op: &MatchOp, literal: &Literal, now: SystemTime) -> bool {
+        match *op {
+            MatchOp::Regex => return self.matches_pattern(&|s| literal.regex_matches(s)),
+            MatchOp::Glob => return self.matches_pattern(&|s| glob_matches(literal.raw(), s)),
+            _ => (),
+        }
+
+        let contains = *op == MatchOp::Contains;
+        match *self {
+            Value::String(s) if contains => s.contains(literal.raw()),
+            Value::List(ref items) if contains => items
+                .iter()
+                .any(|i| i.matches_at(&MatchOp::Equivalence, literal, now)),
+            _ => self.compare_at(literal, now).is_some_and(|ordering| op_matches(op, ordering)),
+        }
+    }
+
+    /// Match the value against a pattern
+    ///
+    /// Only strings and lists containing matching strings match patterns.
+    ///
+    fn matches_pattern(&self, pattern: &dyn Fn(&str) -> bool) -> bool {
+        match *self {
+            Value::String(s) => pattern(s),
+            Value::List(ref items) => items.iter().any(|i| i.matches_pattern(pattern)),
+            _ => false,
+        }
+    }
+}
+
+
+/// Check whether an ordering satisfies a match operator
+///
+/// "Contains" is treated as equivalent to equality.
+///
+fn op_matches(op: &MatchOp, ordering: Ordering) -> bool {
+    match *op {
+        MatchOp::Equivalence | MatchOp::Contains => ordering == Ordering::Equal,
+        MatchOp::LowerThan          => ordering == Ordering::Less,
+        MatchOp::GreaterThan        => ordering == Ordering::Greater,
+        MatchOp::LowerThanOrEqual   => ordering != Ordering::Greater,
+        MatchOp::GreaterThanOrEqual => ordering != Ordering::Less,
+        MatchOp::Regex | MatchOp::Glob => false,
+    }
+}
+
+
+/// Check whether a string matches a glob pattern
+///
+/// Within the pattern, `*` matches any sequence of characters and `?` any
+/// single character. The pattern must match the entire string.
+///
+fn glob_matches(pattern: &str, string: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let string: Vec<char> = string.chars().collect();
+
+    // We match greedily. If we encounter a mismatch, we retry from the last
+    // `*`, letting it consume one more character.
+    let (mut p, mut s) = (0, 0);
+    let mut retry = None;
+    while s < string.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                retry = Some((p, s));
+                p += 1;
+            },
+            Some(c) if *c == '?' || *c == string[s] => {
+                p += 1;
+                s += 1;
+            },
+            _ => match retry {
+                Some((star, consumed)) => {
+                    retry = Some((star, consumed + 1));
+                    p = star + 1;
+                    s = consumed + 1;
+                },
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+
+
+
+/// Conversion of native values to `Value`s
+///
+pub trait ToValue {
+    fn to_value(&self) -> Value<'_>;
+}
+
+
+impl ToValue for String {
+    fn to_value(&self) -> Value<'_> {
+        Value::String(self.as_str())
+    }
+}
+
+
+impl ToValue for &str {
+    fn to_value(&self) -> Value<'_> {
+        Value::String(self)
+    }
+}
+
+
+impl ToValue for i64 {
+    fn to_value(&self) -> Value<'_> {
+        Value::Integer(*self)
+    }
+}
+
+
+impl ToValue for f64 {
+    fn to_value(&self) -> Value<'_> {
+        Value::Float(*self)
+    }
+}
+
+
+impl ToValue for bool {
+    fn to_value(&self) -> Value<'_> {
+        Value::Bool(*self)
+    }
+}
+
+
+impl ToValue for SystemTime {
+    fn to_value(&self) -> Value<'_> {
+        Value::Timestamp(*self)
+    }
+}
+
+
+impl ToValue for Duration {
+    fn to_value(&self) -> Value<'_> {
+        Value::Duration(*self)
+    }
+}
+
+
+impl ToValue for Version {
+    fn to_value(&self) -> Value<'_> {
+        Value::Version(self.clone())
+    }
+}
+
+
+impl<T> ToValue for Vec<T>
+    where T: ToValue
+{
+    fn to_value(&self) -> Value<'_> {
+        Value::List(self.iter().map(ToValue::to_value).collect())
+    }
+}
+
+
+impl<'v> ToValue for Value<'v> {
+    fn to_value(&self) -> Value<'_> {
+        self.clone()
+    }
+}
+
+
+
+
+/// Literal of a condition atom
+///
+/// The literal is kept in its string representation. Interpretations as other
+/// types are computed when the literal is created. Literals are compared and
+/// hashed based on their string representation only.
+///
+#[derive(Debug, Clone)]
+pub struct Literal {
+    raw: String,
+    integer: Option<i64>,
+    float: Option<f64>,
+    boolean: Option<bool>,
+    time: Option<TimeLiteral>,
+    duration: Option<Duration>,
+    version: Option<Version>,
+    #[cfg(feature = "regex")]
+    regex: Option<Regex>,
+}
+
+
+impl Literal {
+    /// Create a literal for use with a given match operator
+    ///
+    /// Contrary to the conversion from a `&str`, this function precompiles
+    /// the literal as a regular expression if the operator is `MatchOp::Regex`.
+    /// If the literal is not a valid regular expression, an
+    /// `ErrorKind::InvalidPattern` error is returned. If the library is
+    /// compiled without support for regular expressions, an
+    /// `ErrorKind::OperatorUnsupported` error is returned instead.
+    ///
+    pub fn new(raw: &str, op: &MatchOp) -> Result<Self> {
+        let mut retval = Self::from(raw);
+        if *op == MatchOp::Regex {
+            retval.compile_regex()?;
+        }
+        Ok(retval)
+    }
+
+    /// Retrieve the literal's string representation
+    ///
+    pub fn raw(&self) -> &str {
+        self.raw.as_str()
+    }
+
+    /// Interpret the literal as an integer
+    ///
+    pub fn integer(&self) -> Option<i64> {
+        self.integer
+    }
+
+    /// Interpret the literal as a floating point number
+    ///
+    pub fn float(&self) -> Option<f64> {
+        self.float
+    }
+
+    /// Interpret the literal as a boolean
+    ///
+    pub fn boolean(&self) -> Option<bool> {
+        self.boolean
+    }
+
+    /// Check whether a string matches the literal as a regular expression
+    ///
+    /// If the literal was not precompiled via `new()`, it is compiled on the
+    /// fly. Invalid regular expressions never match.
+    ///
+    #[cfg(feature = "regex")]
+    fn regex_matches(&self, string: &str) -> bool {
+        match self.regex {
+            Some(ref regex) => regex.is_match(string),
+            None => Regex::new(self.raw()).is_ok_and(|r| r.is_match(string)),
+        }
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn regex_matches(&self, _string: &str) -> bool {
+        false
+    }
+
+    #[cfg(feature = "regex")]
+    fn compile_regex(&mut self) -> Result<()> {
+        let regex = Regex::new(self.raw())
+            .map_err(|_| Error::from(ErrorKind::InvalidPattern(self.raw.clone())))?;
+        self.regex = Some(regex);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn compile_regex(&mut self) -> Result<()> {
+        Err(Error::from(ErrorKind::OperatorUnsupported(MatchOp::Regex)))
+    }
+
+    /// Interpret the literal as a timestamp
+    ///
+    /// Points in time relative to the current time are computed using the
+    /// system clock.
+    ///
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        self.timestamp_at(SystemTime::now())
+    }
+
+    /// Interpret the literal as a timestamp, given the current time
+    ///
+    /// The literal is interpreted as an ISO-8601 date or date-time, a point in
+    /// time relative to `now` or as the number of seconds since the Unix epoch.
+    /// Points in time which can not be represented yield `None`.
+    ///
+    pub fn timestamp_at(&self, now: SystemTime) -> Option<SystemTime> {
+        if let Some(time) = self.time {
+            return time.at(now);
+        }
+        self.integer.and_then(epoch_offset)
+    }
+
+    /// Interpret the literal as a duration
+    ///
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// Interpret the literal as a semantic version
+    ///
+    pub fn version(&self) -> Option<&Version> {
+        self.version.as_ref()
+    }
+}
+
+
+impl From<&str> for Literal {
+    fn from(raw: &str) -> Self {
+        Self {
+            raw: raw.to_owned(),
+            integer: raw.parse().ok(),
+            float: raw.parse().ok(),
+            boolean: raw.parse().ok(),
+            time: TimeLiteral::parse(raw),
+            duration: parse_duration(raw),
+            version: Version::parse(raw),
+            #[cfg(feature = "regex")]
+            regex: None,
+        }
+    }
+}
+
+
+impl PartialEq for Literal {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+
+impl Eq for Literal {}
+
+
+impl Hash for Literal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw.hash(state)
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn matches(value: Value, op: MatchOp, literal: &str) -> bool {
+        value.matches(&op, &literal.into())
+    }
+
+    #[test]
+    fn numbers() {
+        assert!(matches(Value::Integer(3), MatchOp::LowerThan, "3.5"));
+        assert!(matches(Value::Integer(3), MatchOp::Equivalence, "3.0"));
+        assert!(matches(Value::Float(2.5), MatchOp::GreaterThanOrEqual, "2"));
+        assert!(matches(Value::Float(2.5), MatchOp::Contains, "2.5"));
+        assert!(!matches(Value::Float(2.5), MatchOp::Equivalence, "foo"));
+        assert!(!matches(Value::Float(f64::NAN), MatchOp::LowerThanOrEqual, "1"));
+        assert!(!matches(Value::Float(f64::NAN), MatchOp::GreaterThan, "1"));
+    }
+
+    #[test]
+    fn partial_order() {
+        // Booleans are not ordered, but `<=` and `>=` include equality
+        assert!(matches(Value::Bool(true), MatchOp::LowerThanOrEqual, "true"));
+        assert!(!matches(Value::Bool(true), MatchOp::LowerThan, "true"));
+        assert!(!matches(Value::Bool(false), MatchOp::LowerThan, "true"));
+        assert!(!matches(Value::Bool(false), MatchOp::GreaterThanOrEqual, "true"));
+        assert!(matches(Value::Bool(false), MatchOp::Contains, "false"));
+
+        // Lists are only compared via "contains"
+        let list = || Value::List(vec![Value::String("bug"), Value::Integer(3)]);
+        assert!(matches(list(), MatchOp::Contains, "3"));
+        assert!(!matches(list(), MatchOp::Contains, "bu"));
+        assert!(!matches(list(), MatchOp::Equivalence, "bug"));
+        assert!(!matches(list(), MatchOp::GreaterThanOrEqual, "bug"));
+    }
+
+    #[test]
+    fn literals() {
+        let literal = Literal::from("42");
+        assert_eq!(literal.raw(), "42");
+        assert_eq!(literal.integer(), Some(42));
+        assert_eq!(literal.float(), Some(42.0));
+        assert_eq!(literal.boolean(), None);
+        assert!(literal == Literal::from("42"));
+        assert!(literal != Literal::from("42.0"));
+    }
+
+    #[test]
+    fn globs() {
+        assert!(matches(Value::String("area/ui"), MatchOp::Glob, "area/*"));
+        assert!(matches(Value::String("area/"), MatchOp::Glob, "area/*"));
+        assert!(!matches(Value::String("xarea/ui"), MatchOp::Glob, "area/*"));
+        assert!(matches(Value::String("abcbd"), MatchOp::Glob, "a*b?"));
+        assert!(matches(Value::String("abcbdb"), MatchOp::Glob, "*b*b"));
+        assert!(!matches(Value::String("abc"), MatchOp::Glob, "a?"));
+        assert!(matches(Value::String("ä"), MatchOp::Glob, "?"));
+        assert!(matches(Value::String(""), MatchOp::Glob, "**"));
+
+        let labels = || Value::List(vec![Value::String("bug"), Value::String("area/ui")]);
+        assert!(matches(labels(), MatchOp::Glob, "area/*"));
+        assert!(!matches(labels(), MatchOp::Glob, "prio/*"));
+        assert!(!matches(Value::Integer(3), MatchOp::Glob, "*"));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regexes() {
+        assert!(matches(Value::String("[RFC] Foo"), MatchOp::Regex, "^\\[RFC\\]"));
+        assert!(!matches(Value::String("Re: [RFC] Foo"), MatchOp::Regex, "^\\[RFC\\]"));
+        assert!(matches(Value::List(vec![Value::String("area/ui")]), MatchOp::Regex, "^area/"));
+        assert!(!matches(Value::String("foo"), MatchOp::Regex, "("));
+
+        assert!(Literal::new("^foo$", &MatchOp::Regex).is_ok());
+        assert!(Literal::new("(", &MatchOp::Regex).is_err());
+        assert!(Literal::new("(", &MatchOp::Glob).is_ok());
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn regexes() {
+        assert!(!matches(Value::String("foo"), MatchOp::Regex, "foo"));
+        assert!(Literal::new("foo", &MatchOp::Regex).is_err());
+    }
+
+    #[test]
+    fn versions() {
+        let version = || Value::Version(Version::parse("1.10.0").expect("Failed to parse version"));
+        assert!(matches(version(), MatchOp::GreaterThanOrEqual, "1.4.0"));
+        assert!(matches(version(), MatchOp::GreaterThan, "1.10.0-rc.1"));
+        assert!(matches(version(), MatchOp::Equivalence, "v1.10"));
+        assert!(!matches(version(), MatchOp::LowerThan, "1.9.0"));
+        assert!(!matches(version(), MatchOp::LowerThan, "foo"));
+    }
+
+    #[test]
+    fn times() {
+        let due = || Value::Timestamp(UNIX_EPOCH + Duration::from_secs(1796083200));
+        assert!(matches(due(), MatchOp::Equivalence, "2026-12-01"));
+        assert!(matches(due(), MatchOp::LowerThan, "2026-12-01T00:00:01Z"));
+        assert!(matches(due(), MatchOp::GreaterThan, "1796083199"));
+        assert!(matches(Value::Timestamp(UNIX_EPOCH), MatchOp::LowerThan, "now-1d"));
+
+        let literal = Literal::from("now-14d");
+        let now = UNIX_EPOCH + Duration::from_secs(30 * 86400);
+        assert_eq!(literal.timestamp_at(now), Some(UNIX_EPOCH + Duration::from_secs(16 * 86400)));
+
+        // Points in time out of range are not comparable
+        assert_eq!(Literal::from("now+18446744073709551615s").timestamp_at(now), None);
+        assert!(!matches(due(), MatchOp::LowerThan, "now+18446744073709551615s"));
+        assert!(!matches(due(), MatchOp::GreaterThan, "now-18446744073709551615s"));
+        assert!(!matches(due(), MatchOp::LowerThan, "-9223372036854775808"));
+        assert_eq!(
+            matches(due(), MatchOp::GreaterThan, "-9223372036854775808"),
+            Literal::from("-9223372036854775808").timestamp().is_some()
+        );
+
+        let age = || Value::Duration(Duration::from_secs(40 * 86400));
+        assert!(matches(age(), MatchOp::GreaterThan, "30d"));
+        assert!(matches(age(), MatchOp::LowerThanOrEqual, "P40D"));
+        assert!(!matches(age(), MatchOp::GreaterThan, "6w"));
+    }
+}
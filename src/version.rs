@@ -0,0 +1,245 @@
+// Issue states
+//
+// Copyright (c) 2018 Julian Ganz
+//
+// MIT License
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+
+//! Semantic versions
+//!
+//! This module provides the `Version` type, representing a semantic version as
+//! specified by [Semantic Versioning 2.0.0](https://semver.org/). Versions are
+//! ordered by their precedence.
+//!
+
+use std::cmp::Ordering;
+use std::fmt;
+
+
+
+
+/// Identifier of a pre-release version
+///
+/// Numeric identifiers always have a lower precedence than alphanumeric ones.
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum Identifier {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+
+impl Identifier {
+    /// Parse a dot-separated identifier
+    ///
+    fn parse(string: &str) -> Option<Self> {
+        if string.is_empty() || !string.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return None;
+        }
+        match string.parse() {
+            Ok(n) => Some(Identifier::Numeric(n)),
+            Err(_) => Some(Identifier::Alphanumeric(string.to_owned())),
+        }
+    }
+}
+
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Identifier::Numeric(n) => n.fmt(f),
+            Identifier::Alphanumeric(ref s) => f.write_str(s),
+        }
+    }
+}
+
+
+
+
+/// Semantic version
+///
+/// Versions are compared according to their precedence. Build metadata is not
+/// considered, e.g. two versions differing only in their build metadata are
+/// considered equal.
+///
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// Pre-release identifiers
+    pub pre: Vec<Identifier>,
+    /// Build metadata
+    pub build: Vec<String>,
+}
+
+
+impl Version {
+    /// Create a version without pre-release identifiers or build metadata
+    ///
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {major, minor, patch, pre: Vec::new(), build: Vec::new()}
+    }
+
+    /// Parse a version
+    ///
+    /// In addition to versions conforming to the specification, this function
+    /// accepts versions prefixed with a `v` and versions lacking the minor or
+    /// patch number, which are then assumed to be zero. E.g. `v1.4` is parsed
+    /// as `1.4.0`.
+    ///
+    pub fn parse(string: &str) -> Option<Self> {
+        let string = string.strip_prefix('v').unwrap_or(string);
+        let (string, build) = match string.find('+') {
+            Some(pos) => (&string[..pos], Some(&string[pos + 1..])),
+            None => (string, None),
+        };
+        let (string, pre) = match string.find('-') {
+            Some(pos) => (&string[..pos], Some(&string[pos + 1..])),
+            None => (string, None),
+        };
+
+        let mut numbers = string.split('.').map(|n| {
+            // Leading zeroes are not permitted
+            if n.is_empty() || n.len() > 1 && n.starts_with('0') || !n.chars().all(|c| c.is_ascii_digit()) {
+                None
+            } else {
+                n.parse().ok()
+            }
+        });
+        let major = numbers.next()??;
+        let minor = numbers.next().unwrap_or(Some(0))?;
+        let patch = numbers.next().unwrap_or(Some(0))?;
+        if numbers.next().is_some() {
+            return None;
+        }
+
+        let pre = match pre {
+            Some(pre) => pre.split('.').map(Identifier::parse).collect::<Option<_>>()?,
+            None => Vec::new(),
+        };
+        let build = match build {
+            Some(build) => build
+                .split('.')
+                .map(|i| Identifier::parse(i).map(|_| i.to_owned()))
+                .collect::<Option<_>>()?,
+            None => Vec::new(),
+        };
+
+        Some(Self {major, minor, patch, pre, build})
+    }
+}
+
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+
+impl Eq for Version {}
+
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // A pre-release version has a lower precedence than the associated
+        // normal version.
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        for (i, identifier) in self.pre.iter().enumerate() {
+            f.write_str(if i == 0 { "-" } else { "." })?;
+            identifier.fmt(f)?;
+        }
+        for (i, identifier) in self.build.iter().enumerate() {
+            f.write_str(if i == 0 { "+" } else { "." })?;
+            f.write_str(identifier)?;
+        }
+        Ok(())
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(string: &str) -> Version {
+        Version::parse(string).expect("Failed to parse version")
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse("1.4.0").to_string(), "1.4.0");
+        assert_eq!(parse("v1.4").to_string(), "1.4.0");
+        assert_eq!(parse("2").to_string(), "2.0.0");
+        assert_eq!(parse("1.0.0-alpha.1+build.5").to_string(), "1.0.0-alpha.1+build.5");
+
+        for invalid in &["", "1.", "1..2", "1.2.3.4", "01.2.3", "1.2.x", "1.2.3-", "1.2.3-a..b", "1.2.3+"] {
+            assert!(Version::parse(invalid).is_none(), "Parsed invalid version {}", invalid);
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        // Example from the specification
+        let versions = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.4.0",
+            "1.10.0",
+            "2.0.0",
+        ];
+        for pair in versions.windows(2) {
+            assert!(parse(pair[0]) < parse(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+
+        assert_eq!(parse("1.0.0+a"), parse("1.0.0+b"));
+    }
+}
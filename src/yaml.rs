@@ -30,19 +30,26 @@
 //! must be either the name of a state or a mapping containing:
 //! * a "name" entry denoting the name of the state,
 //! * an optional "conditions" entry containing conditions, as a sequence of
-//!   strings,
+//!   strings or maps composing conditions via "all", "any" or "not" entries,
 //! * an optional "overrides" entry containing a sequence of state names
 //!   apprearing _prior_ to the current issue state in the toplevel sequence,
 //!   and
 //! * an optional "extends" entry containing a sequence of state names
 //!   apprearing _prior_ to the current issue state in the toplevel sequence.
 //!
+//! Issue states may also be written to a YAML document using
+//! `emit_issue_states()`, provided that the conditions implement `ToYaml`.
+//!
 
+use std::fmt;
 use std::result::Result as RResult;
 use std::sync::Arc;
-use yaml_rust::{parser, scanner};
+use yaml_rust::{emitter, parser, scanner, Yaml};
+use yaml_rust::yaml::Hash;
 
 use condition;
+use expression::Expression;
+use metadata::{Metadata, MetadataCondition};
 use resolution::IssueStateSet;
 use state;
 
@@ -75,15 +82,13 @@ pub fn parse_issue_states<R, C, F>(
     cond_factory: F
 ) -> ParseResult<IssueStateSet<C>>
     where R: Iterator<Item = char>,
-          C: condition::Condition + Sized,
           F: condition::ConditionFactory<C>,
 {
     // Skip the beginning of the document
-    while match parser.peek()? {
-        (parser::Event::StreamStart, _) => true,
-        (parser::Event::DocumentStart, _) => true,
-        _ => false,
-    } { parser.next()?; }
+    while matches!(
+        parser.peek()?,
+        (parser::Event::StreamStart, _) | (parser::Event::DocumentStart, _)
+    ) { parser.next()?; }
 
     // Identify the start of the sequence
     match parser.peek()? {
@@ -125,7 +130,6 @@ fn parse_issue_state_map<R, C, F>(
     cond_factory: &F
 ) -> ParseResult<state::IssueState<C>>
     where R: Iterator<Item = char>,
-          C: condition::Condition + Sized,
           F: condition::ConditionFactory<C>,
 {
     let mut name = Default::default();
@@ -149,16 +153,7 @@ fn parse_issue_state_map<R, C, F>(
                     "Expected state name as scalar")
                 ),
             },
-            "conditions" => for item in StringIter::new(parser) {
-                let (cond, marker) = item?;
-                conditions.push(cond_factory.parse_condition(cond.as_str()).map_err(|err| {
-                    let s = err.to_string();
-                    scanner::ScanError::new(
-                        marker,
-                        s.as_str()
-                    )
-                })?);
-            }
+            "conditions" => conditions.extend(parse_conditions(parser, cond_factory)?),
             "overrides" => parse_state_relations(
                 &mut relations,
                 parser,
@@ -185,6 +180,124 @@ fn parse_issue_state_map<R, C, F>(
 }
 
 
+/// Function for parsing a list of conditions
+///
+/// The conditions may be given either as a single node or as a sequence of
+/// nodes. Each of the nodes is either a scalar representing a condition atom or
+/// a map representing a composition of conditions.
+///
+fn parse_conditions<R, C, F>(
+    parser: &mut parser::Parser<R>,
+    cond_factory: &F
+) -> ParseResult<Vec<C>>
+    where R: Iterator<Item = char>,
+          F: condition::ConditionFactory<C>,
+{
+    let mut retval = Vec::default();
+
+    match parser.next()? {
+        (parser::Event::SequenceStart(_), _) => loop {
+            match parser.next()? {
+                (parser::Event::SequenceEnd, _) => break, // We hit the end of the sequence
+                (event, marker) => retval.extend(
+                    parse_condition_node(parser, event, marker, cond_factory)?
+                ),
+            }
+        },
+        (event, marker) => retval.extend(
+            parse_condition_node(parser, event, marker, cond_factory)?
+        ),
+    }
+
+    Ok(retval)
+}
+
+
+/// Function for parsing a single condition node
+///
+/// The `event` is expected to be the event starting the node.
+///
+fn parse_condition_node<R, C, F>(
+    parser: &mut parser::Parser<R>,
+    event: parser::Event,
+    marker: scanner::Marker,
+    cond_factory: &F
+) -> ParseResult<Vec<C>>
+    where R: Iterator<Item = char>,
+          F: condition::ConditionFactory<C>,
+{
+    match event {
+        parser::Event::Scalar(atom, _, _, _) => cond_factory
+            .parse_condition(atom.as_str())
+            .map(|cond| vec![cond])
+            .map_err(|err| factory_error(marker, err)),
+        parser::Event::MappingStart(_) => parse_condition_map(parser, cond_factory),
+        _ => Err(scanner::ScanError::new(
+            marker,
+            "Expected condition as either scalar or map"
+        )),
+    }
+}
+
+
+/// Function for parsing a composition of conditions represented as a map
+///
+/// Each entry of the map results in one condition. The key of the entry
+/// determines how the conditions in the entry's value are composed:
+/// * "all" denotes their conjunction,
+/// * "any" denotes their disjunction and
+/// * "not" denotes the negation of their conjunction.
+///
+fn parse_condition_map<R, C, F>(
+    parser: &mut parser::Parser<R>,
+    cond_factory: &F
+) -> ParseResult<Vec<C>>
+    where R: Iterator<Item = char>,
+          F: condition::ConditionFactory<C>,
+{
+    let mut retval = Vec::default();
+
+    loop {
+        // Try to extract the key of the entry
+        let (key, marker) = match parser.next()? {
+            (parser::Event::MappingEnd, _) => break, // We hit the end of the map
+            (parser::Event::Scalar(key, _, _, _), marker) => (key, marker),
+            (_, marker) => return Err(scanner::ScanError::new(marker, "Expected scalar key")),
+        };
+
+        let mut conditions = parse_conditions(parser, cond_factory)?;
+        let condition = match key.as_str() {
+            "all" => cond_factory.make_conjunction(conditions),
+            "any" => cond_factory.make_disjunction(conditions),
+            "not" => if conditions.len() == 1 {
+                cond_factory.make_negation(conditions.remove(0))
+            } else {
+                cond_factory
+                    .make_conjunction(conditions)
+                    .and_then(|cond| cond_factory.make_negation(cond))
+            },
+            _ => return Err(scanner::ScanError::new(
+                marker,
+                "Expected either 'all', 'any' or 'not'"
+            )),
+        };
+
+        retval.push(condition.map_err(|err| factory_error(marker, err))?);
+    }
+
+    Ok(retval)
+}
+
+
+/// Create a `ScanError` from an error reported by a `ConditionFactory`
+///
+fn factory_error<E>(marker: scanner::Marker, err: E) -> scanner::ScanError
+    where E: ToString
+{
+    scanner::ScanError::new(marker, err.to_string().as_str())
+}
+
+
 /// Function for parsing relations from a sequence of scalars
 ///
 fn parse_state_relations<R, C>(
@@ -194,7 +307,6 @@ fn parse_state_relations<R, C>(
     relation: state::StateRelation
 ) -> ParseResult<()>
     where R: Iterator<Item = char>,
-          C: condition::Condition + Sized,
 {
     for item in StringIter::new(parser) {
         let (name, marker) = item?;
@@ -212,6 +324,105 @@ fn parse_state_relations<R, C>(
 }
 
 
+/// Conversion of conditions to YAML nodes
+///
+/// Conditions need to implement this trait in order to be emitted. The node
+/// created must be accepted by the parser as a condition, e.g. it should be
+/// the string representation of the condition atom.
+///
+pub trait ToYaml {
+    /// Create the YAML node representing `self`
+    ///
+    fn to_yaml(&self) -> Yaml;
+}
+
+
+impl<C> ToYaml for Expression<C>
+    where C: ToYaml
+{
+    fn to_yaml(&self) -> Yaml {
+        let (key, value) = match *self {
+            Expression::Atom(ref c) => return c.to_yaml(),
+            Expression::And(ref e) => ("all", Yaml::Array(e.iter().map(ToYaml::to_yaml).collect())),
+            Expression::Or(ref e) => ("any", Yaml::Array(e.iter().map(ToYaml::to_yaml).collect())),
+            Expression::Not(ref e) => ("not", e.to_yaml()),
+        };
+
+        let mut map = Hash::new();
+        map.insert(Yaml::String(key.to_owned()), value);
+        Yaml::Hash(map)
+    }
+}
+
+
+impl<M> ToYaml for MetadataCondition<M>
+    where M: Metadata
+{
+    fn to_yaml(&self) -> Yaml {
+        Yaml::String(self.to_string())
+    }
+}
+
+
+/// Emit issue states as a YAML document
+///
+/// The states are written as a sequence, in the order in which they appear in
+/// the set. States without any conditions or relations are written as plain
+/// strings. All other states are written as maps.
+///
+pub fn emit_issue_states<C, W>(
+    states: &IssueStateSet<C>,
+    writer: &mut W
+) -> RResult<(), emitter::EmitError>
+    where C: ToYaml,
+          W: fmt::Write,
+{
+    let doc = Yaml::Array(states.iter().map(|state| state_to_yaml(state)).collect());
+    emitter::YamlEmitter::new(writer).dump(&doc)
+}
+
+
+/// Convert a single issue state to a YAML node
+///
+fn state_to_yaml<C>(state: &state::IssueState<C>) -> Yaml
+    where C: ToYaml,
+{
+    let name = Yaml::String(state.name().clone());
+    if state.conditions.is_empty() && state.relations.is_empty() {
+        return name;
+    }
+
+    let mut map = Hash::new();
+    map.insert(Yaml::String("name".to_owned()), name);
+
+    if !state.conditions.is_empty() {
+        map.insert(
+            Yaml::String("conditions".to_owned()),
+            Yaml::Array(state.conditions.iter().map(ToYaml::to_yaml).collect())
+        );
+    }
+
+    let relations = |relation: state::StateRelation| state
+        .relations
+        .iter()
+        .filter(|item| *item.1 == relation)
+        .map(|item| Yaml::String(item.0.name().clone()))
+        .collect::<Vec<_>>();
+
+    let overrides = relations(state::StateRelation::Overrides);
+    if !overrides.is_empty() {
+        map.insert(Yaml::String("overrides".to_owned()), Yaml::Array(overrides));
+    }
+
+    let extends = relations(state::StateRelation::Extends);
+    if !extends.is_empty() {
+        map.insert(Yaml::String("extends".to_owned()), Yaml::Array(extends));
+    }
+
+    Yaml::Hash(map)
+}
+
+
 /// Iterator for iterating over the scalars in a sequence
 ///
 /// This iterator allows convenient iteration over a sequence, assuming that the
@@ -242,7 +453,7 @@ impl<'p, R> StringIter<'p, R>
     /// where you expect a list.
     ///
     fn new(parser: &'p mut parser::Parser<R>) -> Self {
-        Self {parser: parser, state: SequenceParseState::Start}
+        Self {parser, state: SequenceParseState::Start}
     }
 
     /// Given that we are within a sequence, extract the next item
@@ -311,6 +522,7 @@ enum SequenceParseState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use expression::{Expression, ExpressionFactory};
     use test::{TestCond, TestCondFactory};
 
     // Convenience function for encapsulating boilerplate for each test
@@ -417,4 +629,97 @@ mod tests {
 
         assert!(iter.next().is_none());
     }
+
+    #[test]
+    fn composed_conditions() {
+        let mut parser = parser::Parser::new("---
+  - name: foobar
+    conditions:
+      - foo
+      - any: [bar, baz]
+      - not:
+          all: [bar, baz]
+...".chars());
+        let result: IssueStateSet<Expression<TestCond>> = parse_issue_states(
+            &mut parser,
+            ExpressionFactory::<TestCondFactory>::default()
+        ).expect("Failed to parse document");
+        let mut iter = result.iter();
+
+        let state = iter
+            .next()
+            .expect("Parse result does not contain expected state.");
+        assert_eq!(state.name(), "foobar");
+
+        let atom = |name: &'static str| Expression::Atom(TestCond::from(name));
+        assert_eq!(state.conditions, vec![
+            atom("foo"),
+            Expression::Or(vec![atom("bar"), atom("baz")]),
+            Expression::Not(Box::new(Expression::And(vec![atom("bar"), atom("baz")]))),
+        ]);
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn unsupported_composition() {
+        let mut parser = parser::Parser::new("- name: foobar\n  conditions: {any: [foo, bar]}".chars());
+        assert!(parse_issue_states(&mut parser, TestCondFactory::default()).is_err());
+    }
+
+    #[test]
+    fn emit() {
+        let mut parser = parser::Parser::new("---
+- new
+- name: acknowledged
+  conditions:
+    - acked
+  overrides:
+    - new
+- name: assigned
+  conditions:
+    - assigned
+    - any:
+        - foo
+        - not: bar
+  extends:
+    - acknowledged
+...".chars());
+        let states: IssueStateSet<Expression<TestCond>> = parse_issue_states(
+            &mut parser,
+            ExpressionFactory::<TestCondFactory>::default()
+        ).expect("Failed to parse document");
+
+        let mut out = String::new();
+        emit_issue_states(&states, &mut out).expect("Failed to emit document");
+        assert_eq!(out, "---
+- new
+- name: acknowledged
+  conditions:
+    - acked
+  overrides:
+    - new
+- name: assigned
+  conditions:
+    - assigned
+    - any:
+        - foo
+        - not: bar
+  extends:
+    - acknowledged");
+
+        let mut parser = parser::Parser::new(out.chars());
+        let reparsed: IssueStateSet<Expression<TestCond>> = parse_issue_states(
+            &mut parser,
+            ExpressionFactory::<TestCondFactory>::default()
+        ).expect("Failed to parse emitted document");
+        for (state, other) in states.iter().zip(reparsed.iter()) {
+            assert_eq!(state.name(), other.name());
+            assert_eq!(state.conditions, other.conditions);
+            assert_eq!(
+                state.relations.iter().map(|(s, r)| (s.name(), r)).collect::<Vec<_>>(),
+                other.relations.iter().map(|(s, r)| (s.name(), r)).collect::<Vec<_>>()
+            );
+        }
+    }
 }